@@ -3,7 +3,10 @@ extern crate criterion;
 
 use criterion::{BatchSize, Criterion, criterion_group};
 
-use fsst_rust::{encode_all_strings, read_string_lines};
+use fsst_rust::{
+    build_table_by_sampling, build_table_from_bytes_sampling, encode_all_strings, encode_buffer, read_string_lines,
+};
+use fsst_rust::core::codec::Encoder;
 
 fn bench_compress(c: &mut Criterion) {
     let mut group = c.benchmark_group("ps_comment_compress");
@@ -24,6 +27,25 @@ fn bench_compress(c: &mut Criterion) {
         )
     });
 
+    let strings = data_setup();
+    let symbol_table = build_table_by_sampling(&strings);
+    let encoder = Encoder::from_table(&symbol_table);
+    group.bench_with_input("fsst_encode_only", group_test_data_path, |b, _| {
+        b.iter(|| {
+            for str in &strings {
+                encoder.encode_bytes(str.as_bytes());
+            }
+        })
+    });
+
+    group.bench_with_input("fsst_encode_only_fast", group_test_data_path, |b, _| {
+        b.iter(|| {
+            for str in &strings {
+                encoder.encode_bytes_fast(str.as_bytes());
+            }
+        })
+    });
+
     group.bench_with_input("zstd", group_test_data_path, |b, _| {
         b.iter_batched(
             data_setup,
@@ -35,7 +57,62 @@ fn bench_compress(c: &mut Criterion) {
             BatchSize::SmallInput,
         )
     });
+
+    group.bench_with_input("lz4", group_test_data_path, |b, _| {
+        b.iter_batched(
+            data_setup,
+            |s| {
+                for str in s {
+                    lz4_flex::compress_prepend_size(str.as_bytes());
+                }
+            },
+            BatchSize::SmallInput,
+        )
+    });
+
+    group.bench_with_input("snappy", group_test_data_path, |b, _| {
+        b.iter_batched(
+            data_setup,
+            |s| {
+                let mut encoder = snap::raw::Encoder::new();
+                for str in s {
+                    encoder.compress_vec(str.as_bytes()).unwrap();
+                }
+            },
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+/// one buffer of the concatenated lines against the lines encoded one by one, the symbols of the
+/// buffer can span the line ends but no line can be decoded on its own
+fn bench_buffer_vs_lines(c: &mut Criterion) {
+    let mut group = c.benchmark_group("ps_comment_buffer_vs_lines");
+    let group_test_data_path = "assets/test_data/ps_comment";
+    let mut strings = read_string_lines(group_test_data_path).unwrap();
+    strings.truncate(1000);
+    let buffer = strings.concat();
+
+    let (_, encodings) = encode_all_strings(&strings);
+    let lines_bytes = encodings.iter().map(|e| e.len()).sum::<usize>();
+    let buffer_bytes = encode_buffer(&build_table_from_bytes_sampling(buffer.as_bytes()), buffer.as_bytes()).len();
+    println!(
+        "per line: {:.4}x, one buffer: {:.4}x",
+        buffer.len() as f64 / lines_bytes as f64,
+        buffer.len() as f64 / buffer_bytes as f64
+    );
+
+    group.bench_with_input("fsst_per_line", group_test_data_path, |b, _| {
+        b.iter(|| encode_all_strings(&strings))
+    });
+
+    group.bench_with_input("fsst_buffer", group_test_data_path, |b, _| {
+        b.iter(|| {
+            let symbol_table = build_table_from_bytes_sampling(buffer.as_bytes());
+            encode_buffer(&symbol_table, buffer.as_bytes())
+        })
+    });
 }
 
-criterion_group!(benches, bench_compress);
+criterion_group!(benches, bench_compress, bench_buffer_vs_lines);
 criterion_main!(benches);
\ No newline at end of file
@@ -42,6 +42,48 @@ fn bench_decompress(c: &mut Criterion) {
             BatchSize::SmallInput,
         )
     });
+
+    group.bench_with_input("lz4", group_test_data_path, |b, path| {
+        b.iter_batched(
+            || {
+                let mut strings = read_string_lines(path).unwrap();
+                strings.truncate(1000);
+                let mut encodings = Vec::with_capacity(1000);
+                for str in strings {
+                    encodings.push(lz4_flex::compress_prepend_size(str.as_bytes()));
+                }
+                encodings
+            },
+            |encodings| {
+                for encode in encodings {
+                    lz4_flex::decompress_size_prepended(encode.as_slice()).unwrap();
+                }
+            },
+            BatchSize::SmallInput,
+        )
+    });
+
+    group.bench_with_input("snappy", group_test_data_path, |b, path| {
+        b.iter_batched(
+            || {
+                let mut strings = read_string_lines(path).unwrap();
+                strings.truncate(1000);
+                let mut encoder = snap::raw::Encoder::new();
+                let mut encodings = Vec::with_capacity(1000);
+                for str in strings {
+                    encodings.push(encoder.compress_vec(str.as_bytes()).unwrap());
+                }
+                encodings
+            },
+            |encodings| {
+                let mut decoder = snap::raw::Decoder::new();
+                for encode in encodings {
+                    decoder.decompress_vec(encode.as_slice()).unwrap();
+                }
+            },
+            BatchSize::SmallInput,
+        )
+    });
 }
 
 criterion_group!(benches, bench_decompress);
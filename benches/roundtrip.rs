@@ -0,0 +1,32 @@
+#[macro_use]
+extern crate criterion;
+
+use criterion::{BatchSize, Criterion, criterion_group, Throughput};
+
+use fsst_rust::{decode_all_strings, encode_all_strings, read_string_lines};
+
+/// build the table, encode every row and decode them again, the throughput is reported in bytes of
+/// the original rows
+fn bench_roundtrip(c: &mut Criterion) {
+    for name in ["c_name", "l_comment"] {
+        let group_test_data_path = format!("assets/test_data/{}", name);
+        let strings = read_string_lines(&group_test_data_path).unwrap();
+        let mut group = c.benchmark_group(format!("{}_roundtrip", name));
+        group.throughput(Throughput::Bytes(strings.iter().map(|s| s.len() as u64).sum()));
+
+        group.bench_with_input("fsst", &group_test_data_path, |b, _| {
+            b.iter_batched(
+                || strings.clone(),
+                |s| {
+                    let (symbol_table, encodings) = encode_all_strings(&s);
+                    decode_all_strings(&symbol_table, &encodings)
+                },
+                BatchSize::LargeInput,
+            )
+        });
+        group.finish();
+    }
+}
+
+criterion_group!(benches, bench_roundtrip);
+criterion_main!(benches);
@@ -0,0 +1,17 @@
+#![no_main]
+
+use fsst_rust::core::codec::{Decoder, Encoder};
+use fsst_rust::core::symbol_table::SymbolTableBuilder;
+use libfuzzer_sys::fuzz_target;
+
+// decode(encode(x)) == x for a table trained on x, with both code widths
+fuzz_target!(|data: &[u8]| {
+    for wide in [false, true] {
+        let symbol_table = SymbolTableBuilder::new().wide_codes(wide).train_from_bytes(data);
+        let encoding = Encoder::from_table(&symbol_table).encode_bytes(data);
+        assert!(encoding.len() <= data.len() * 2);
+        let decoder = Decoder::from_table(&symbol_table);
+        assert_eq!(data, decoder.decode_bytes(&encoding).as_slice());
+        assert_eq!(data, decoder.decode_checked(&encoding).unwrap().as_slice());
+    }
+});
@@ -0,0 +1,59 @@
+use std::env;
+use std::fs;
+use std::process;
+
+use fsst_rust::core::block::{read_block, write_block};
+use fsst_rust::core::codec::Decoder;
+use fsst_rust::core::symbol_table::SymbolTableBuilder;
+use fsst_rust::encode_all_strings;
+
+const USAGE: &str = "usage: fsst compress <in> <out>\n       fsst decompress <in> <out>";
+
+/// compress a UTF-8 text file line by line into a self-describing block, the newlines are kept in
+/// the records so decompressing restores the file byte for byte
+fn compress(input: &str, output: &str) -> Result<(), String> {
+    let text = fs::read_to_string(input).map_err(|e| format!("failed to read {}: {}", input, e))?;
+    let records = text.split_inclusive('\n').map(String::from).collect::<Vec<String>>();
+    let block = if records.is_empty() {
+        write_block(&SymbolTableBuilder::identity_table(), &[])
+    } else {
+        let (symbol_table, encodings) = encode_all_strings(&records);
+        write_block(&symbol_table, &encodings)
+    };
+    fs::write(output, &block).map_err(|e| format!("failed to write {}: {}", output, e))?;
+    println!(
+        "compressed {} bytes into {} bytes ({:.4}x)",
+        text.len(),
+        block.len(),
+        text.len() as f64 / block.len() as f64
+    );
+    Ok(())
+}
+
+fn decompress(input: &str, output: &str) -> Result<(), String> {
+    let block = fs::read(input).map_err(|e| format!("failed to read {}: {}", input, e))?;
+    let (symbol_table, encodings) = read_block(&block).map_err(|e| format!("failed to decode {}: {}", input, e))?;
+    // the block may be crafted, so every record is validated and kept as bytes
+    let decoder = Decoder::from_table(&symbol_table);
+    let mut bytes = Vec::new();
+    for encoding in &encodings {
+        let record = decoder.decode_checked(encoding).map_err(|e| format!("failed to decode {}: {}", input, e))?;
+        bytes.extend_from_slice(&record);
+    }
+    fs::write(output, &bytes).map_err(|e| format!("failed to write {}: {}", output, e))?;
+    println!("decompressed {} bytes into {} bytes", block.len(), bytes.len());
+    Ok(())
+}
+
+fn main() {
+    let args = env::args().collect::<Vec<String>>();
+    let result = match args.iter().map(|a| a.as_str()).collect::<Vec<&str>>()[1..] {
+        ["compress", input, output] => compress(input, output),
+        ["decompress", input, output] => decompress(input, output),
+        _ => Err(USAGE.to_string()),
+    };
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
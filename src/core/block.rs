@@ -0,0 +1,139 @@
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+use crate::core::error::DecodeError;
+use crate::core::symbol_table::{load_symbols, SymbolTable, SymbolTableBuilder};
+use crate::util::crc32c::crc32c;
+use crate::util::varint::{read_varint, write_varint};
+
+/// checksum algorithm of a block, its id is stored in the block so other algorithms can be added
+/// without breaking the blocks already written
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Checksum {
+    /// the block has no checksum
+    None,
+    /// CRC-32C of the block up to the checksum, stored as 4 bytes in little endian
+    Crc32c,
+}
+
+impl Checksum {
+    fn id(self) -> u8 {
+        match self {
+            Checksum::None => 0,
+            Checksum::Crc32c => 1,
+        }
+    }
+
+    fn from_id(id: u8) -> Result<Checksum, DecodeError> {
+        match id {
+            0 => Ok(Checksum::None),
+            1 => Ok(Checksum::Crc32c),
+            _ => Err(DecodeError::UnknownChecksum(id)),
+        }
+    }
+}
+
+/// write a self-describing block that round-trips a whole column of encodings
+/// the layout is | symbol table dump | checksum id | record count | (record length, record encoding) ... | checksum |
+/// where the record count and lengths are varints, the block is checksummed with `Checksum::Crc32c`
+#[allow(clippy::borrowed_box)]
+pub fn write_block(table: &Box<dyn SymbolTable>, encodings: &[Vec<u8>]) -> Vec<u8> {
+    write_block_with_checksum(table, encodings, Checksum::Crc32c)
+}
+
+/// write a block like `write_block` with the given checksum algorithm
+#[allow(clippy::borrowed_box)]
+pub fn write_block_with_checksum(table: &Box<dyn SymbolTable>, encodings: &[Vec<u8>], checksum: Checksum) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(table.dump_len() + encodings.iter().map(|e| e.len() + 2).sum::<usize>() + 9);
+    table.dump_into(&mut buf);
+    buf.push(checksum.id());
+    write_varint(&mut buf, encodings.len() as u64);
+    for encoding in encodings {
+        write_varint(&mut buf, encoding.len() as u64);
+        buf.extend_from_slice(encoding);
+    }
+    match checksum {
+        Checksum::None => {}
+        Checksum::Crc32c => {
+            let crc = crc32c(&buf);
+            buf.extend_from_slice(&crc.to_le_bytes());
+        }
+    }
+    buf
+}
+
+/// read a block written by `write_block`, returns the symbol table and the record encodings
+/// the checksum is verified before the symbol table is built, a block that does not match it
+/// returns `ChecksumMismatch`
+#[allow(clippy::type_complexity)]
+pub fn read_block(buf: &[u8]) -> Result<(Box<dyn SymbolTable>, Vec<Vec<u8>>), DecodeError> {
+    let dumped = load_symbols(buf)?;
+    let mut pos = dumped.end_pos;
+    let checksum = Checksum::from_id(*buf.get(pos).ok_or(DecodeError::TooShort)?)?;
+    pos += 1;
+    let count = read_varint(buf, &mut pos)? as usize;
+    // every record takes at least one byte, so a larger count can only come from a corrupt block
+    let mut records = Vec::with_capacity(count.min(buf.len() - pos));
+    for _ in 0..count {
+        let len = read_varint(buf, &mut pos)? as usize;
+        let end = pos.checked_add(len).ok_or(DecodeError::TooShort)?;
+        records.push(buf.get(pos..end).ok_or(DecodeError::TooShort)?);
+        pos = end;
+    }
+    match checksum {
+        Checksum::None => {}
+        Checksum::Crc32c => {
+            let stored = buf.get(pos..pos + 4).ok_or(DecodeError::TooShort)?;
+            if u32::from_le_bytes([stored[0], stored[1], stored[2], stored[3]]) != crc32c(&buf[..pos]) {
+                return Err(DecodeError::ChecksumMismatch);
+            }
+        }
+    }
+    let table = SymbolTableBuilder::build_from_dumped(dumped)?;
+    Ok((table, records.into_iter().map(|r| r.to_vec()).collect()))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{decode_all_strings, encode_all_strings, read_string_lines};
+    use crate::core::block::{read_block, write_block, write_block_with_checksum, Checksum};
+    use crate::core::error::DecodeError;
+
+    #[test]
+    pub fn test_block() {
+        let mut strings = read_string_lines("assets/test_data/c_name").unwrap();
+        strings.truncate(1000);
+        strings.push(String::new());
+        let (table, encodings) = encode_all_strings(&strings);
+        let block = write_block(&table, &encodings);
+        let (read_table, read_encodings) = read_block(&block).unwrap();
+        assert_eq!(table.dump(), read_table.dump());
+        assert_eq!(encodings, read_encodings);
+        assert_eq!(strings, decode_all_strings(&read_table, &read_encodings));
+
+        assert_eq!(DecodeError::TooShort, read_block(&block[..block.len() - 1]).err().unwrap());
+    }
+
+    #[test]
+    pub fn test_block_checksum() {
+        let strings = read_string_lines("assets/test_data/c_name").unwrap()[..100].to_vec();
+        let (table, encodings) = encode_all_strings(&strings);
+        let block = write_block(&table, &encodings);
+        let unchecked_block = write_block_with_checksum(&table, &encodings, Checksum::None);
+        assert_eq!(block.len(), unchecked_block.len() + 4);
+        assert_eq!(encodings, read_block(&unchecked_block).unwrap().1);
+
+        // flip a bit of the last record
+        let mut corrupt_block = block.clone();
+        let last = corrupt_block.len() - 5;
+        corrupt_block[last] ^= 1;
+        assert_eq!(DecodeError::ChecksumMismatch, read_block(&corrupt_block).err().unwrap());
+        let mut corrupt_block = block.clone();
+        *corrupt_block.last_mut().unwrap() ^= 0x80;
+        assert_eq!(DecodeError::ChecksumMismatch, read_block(&corrupt_block).err().unwrap());
+
+        let mut unknown_block = unchecked_block.clone();
+        unknown_block[table.dump_len()] = 7;
+        assert_eq!(DecodeError::UnknownChecksum(7), read_block(&unknown_block).err().unwrap());
+    }
+}
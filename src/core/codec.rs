@@ -1,163 +1,868 @@
-use crate::core::{bulk_load_u32, CODE_ESCAPE, U64_SIZE};
+use alloc::boxed::Box;
+use alloc::string::{FromUtf8Error, String};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::min;
+use core::fmt::{Debug, Display, Formatter};
+use core::ops::Range;
+
+use crate::core::{bulk_load, bulk_load_u32, CODE_BASE, CODE_ESCAPE, CODE_MASK_WIDE, CODE_MAX_WIDE, U64_SIZE};
+use crate::core::error::{DecodeError, EncodeError};
 use crate::core::symbol::Symbol;
-use crate::core::symbol_table::SymbolTable;
-use crate::util::endian::Endian;
+use crate::core::symbol_table::{load_symbols, PerfectHashSymbolTable, SymbolTable};
+use crate::util::varint::{MAX_VARINT_LEN, read_varint, write_varint};
+
+/// the table type defaults to a trait object, an encoder of a concrete table type calls the table
+/// without dynamic dispatch
+pub struct Encoder<'a, T: SymbolTable + ?Sized = dyn SymbolTable> {
+    symbol_table: &'a T,
+}
 
-pub struct Encoder<'a> {
-    symbol_table: &'a Box<dyn SymbolTable>,
+// derived impls would require the table itself to be `Clone`, but only a reference is copied
+impl<T: SymbolTable + ?Sized> Clone for Encoder<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
 }
 
+impl<T: SymbolTable + ?Sized> Copy for Encoder<'_, T> {}
+
 impl Encoder<'_> {
-    pub fn from_table(table: &Box<dyn SymbolTable>) -> Encoder {
+    #[allow(clippy::borrowed_box)]
+    pub fn from_table(table: &Box<dyn SymbolTable>) -> Encoder<'_> {
+        Encoder { symbol_table: table.as_ref() }
+    }
+
+    /// the most bytes any encoding takes per input byte, an escape and the escaped byte for tables
+    /// with one-byte codes and one two-byte code for wide tables
+    pub const MAX_EXPANSION: usize = 2;
+
+    /// the longest encoding of an input of `input_len` bytes, which is every byte escaped, so a
+    /// buffer of this size never grows in `encode_into`
+    /// no table and no input encode to more, which storage can rely on to size its buffers
+    pub fn max_encoded_len(input_len: usize) -> usize {
+        input_len * Self::MAX_EXPANSION
+    }
+}
+
+impl<T: SymbolTable + ?Sized> Encoder<'_, T> {
+    /// build an encoder borrowing the table directly, which needs no `Box`
+    pub fn from_table_ref(table: &T) -> Encoder<'_, T> {
         Encoder { symbol_table: table }
     }
 
     pub fn encode_str(&self, str: &str) -> Vec<u8> {
-        let mut buf = vec![0; str.len() << 1];
-        let (mut pos_in, mut pos_out) = (0, 0);
-        while pos_in < str.len() {
-            let target = Symbol::from_str(&str[pos_in..]);
-            buf[pos_out + 1] = target.first() as u8;
+        self.encode_bytes(str.as_bytes())
+    }
+
+    /// encode raw bytes, the input does not need to be valid UTF-8
+    pub fn encode_bytes(&self, input: &[u8]) -> Vec<u8> {
+        let mut buf = Vec::new();
+        self.encode_into(input, &mut buf);
+        buf
+    }
+
+    /// encode raw bytes into the given buffer, which is cleared first so its allocation can be
+    /// reused across calls
+    /// returns the number of bytes written
+    pub fn encode_into(&self, input: &[u8], buf: &mut Vec<u8>) -> usize {
+        buf.clear();
+        self.encode_counting_escapes(input, buf).0
+    }
+
+    /// encode raw bytes and count the escape codes emitted, a high share of escapes in a record
+    /// means the table fits it badly
+    /// tables with two-byte codes and the identity table never escape, so the count is 0 for them
+    pub fn encode_with_escape_count(&self, input: &[u8]) -> (Vec<u8>, usize) {
+        let mut buf = Vec::new();
+        let (_, escape_count) = self.encode_counting_escapes(input, &mut buf);
+        (buf, escape_count)
+    }
+
+    /// encode to the end of the given buffer, keeping what it already holds
+    /// returns the number of bytes written together with the number of escapes
+    fn encode_counting_escapes(&self, input: &[u8], buf: &mut Vec<u8>) -> (usize, usize) {
+        let start = buf.len();
+        if self.symbol_table.is_identity() {
+            buf.extend_from_slice(input);
+            return (input.len(), 0);
+        }
+        buf.resize(start + Encoder::max_encoded_len(input.len()), 0);
+        if self.symbol_table.is_wide() {
+            return (self.encode_wide_into(input, buf, start), 0);
+        }
+        let escape = self.symbol_table.escape_code();
+        let (mut pos_in, mut pos_out, mut escape_count) = (0, start, 0);
+        while pos_in < input.len() {
+            let target = Symbol::from_bytes(&input[pos_in..]);
+            let (code, s_len, out_len) = self.symbol_table.encode_for(&target);
+            buf[pos_out] = code_byte(code, escape);
+            if out_len == 2 {
+                // the escaped byte
+                buf[pos_out + 1] = target.first() as u8;
+                escape_count += 1;
+            }
+            pos_out += out_len;
+            pos_in += s_len;
+        }
+        buf.truncate(pos_out);
+        (pos_out - start, escape_count)
+    }
+
+    /// every code of a wide table takes two bytes in little endian
+    fn encode_wide_into(&self, input: &[u8], buf: &mut Vec<u8>, start: usize) -> usize {
+        let (mut pos_in, mut pos_out) = (0, start);
+        while pos_in < input.len() {
+            let target = Symbol::from_bytes(&input[pos_in..]);
             let (code, s_len, out_len) = self.symbol_table.encode_for(&target);
-            buf[pos_out] = code;
+            buf[pos_out..pos_out + 2].copy_from_slice(&code.to_le_bytes());
             pos_out += out_len;
             pos_in += s_len;
         }
         buf.truncate(pos_out);
+        pos_out - start
+    }
+
+    /// encode raw bytes like `encode_bytes`, but the next 8 bytes are loaded once as a word and
+    /// matched directly against the lookup tables of a table with one-byte codes, like the loop of
+    /// the reference FSST encoder, instead of being copied into a new symbol at every step
+    /// `encode_bytes` stays the reference implementation the output is checked against
+    pub fn encode_bytes_fast(&self, input: &[u8]) -> Vec<u8> {
+        let Some(table) = self.symbol_table.as_perfect_hash() else {
+            return self.encode_bytes(input);
+        };
+        let escape = table.escape_code();
+        let mut buf = Vec::with_capacity(Encoder::max_encoded_len(input.len()));
+        let mut pos_in = 0;
+        while pos_in + U64_SIZE <= input.len() {
+            let word = u64::from_ne_bytes(input[pos_in..pos_in + U64_SIZE].try_into().unwrap());
+            pos_in += Self::encode_word(table, word, U64_SIZE, escape, &mut buf);
+        }
+        // the tail is shorter than a word, only its remaining bytes may match a symbol
+        while pos_in < input.len() {
+            let word = bulk_load(&input[pos_in..]);
+            pos_in += Self::encode_word(table, word, input.len() - pos_in, escape, &mut buf);
+        }
+        buf
+    }
+
+    /// encode the longest symbol at the start of the word, which holds `len` valid bytes
+    /// returns the number of input bytes consumed
+    #[inline(always)]
+    fn encode_word(table: &PerfectHashSymbolTable, word: u64, len: usize, escape: u8, buf: &mut Vec<u8>) -> usize {
+        let (code, s_len, escaped) = table.encode_word(word, len);
+        buf.push(code_byte(code, escape));
+        if escaped {
+            buf.push(word as u8);
+        }
+        s_len
+    }
+
+    /// encode raw bytes without using any learned symbol, every byte is written as an escape pair
+    /// (for tables with two-byte codes as its single byte code) so the output decodes with the
+    /// standard `Decoder` whatever the table holds
+    /// it is a guaranteed-correct fallback for inputs the table fits badly, and a cross-check for
+    /// the other encoders
+    pub fn encode_stored(&self, input: &[u8]) -> Vec<u8> {
+        if self.symbol_table.is_identity() {
+            return input.to_vec();
+        }
+        let mut buf = Vec::with_capacity(Encoder::max_encoded_len(input.len()));
+        if self.symbol_table.is_wide() {
+            for &byte in input {
+                buf.extend_from_slice(&(byte as u16).to_le_bytes());
+            }
+        } else {
+            let escape = self.symbol_table.escape_code();
+            for &byte in input {
+                buf.push(escape);
+                buf.push(byte);
+            }
+        }
+        buf
+    }
+
+    /// the exact length `encode_bytes` would return, computed without writing the output
+    pub fn encoded_len(&self, input: &[u8]) -> usize {
+        let (mut pos_in, mut encoded_len) = (0, 0);
+        while pos_in < input.len() {
+            let (_, s_len, out_len) = self.symbol_table.encode_for(&Symbol::from_bytes(&input[pos_in..]));
+            pos_in += s_len;
+            encoded_len += out_len;
+        }
+        encoded_len
+    }
+
+    /// count how often every code is emitted when encoding the inputs, without storing the output
+    /// the counts are indexed by code, escapes are counted at `CODE_ESCAPE` (255) for tables with
+    /// one-byte codes, and every byte is counted as its own code for the identity table
+    pub fn usage_counts(&self, inputs: &[&[u8]]) -> Vec<u64> {
+        let code_space = if self.symbol_table.is_wide() { CODE_MAX_WIDE } else { CODE_BASE };
+        let mut counts = vec![0u64; code_space as usize];
+        for input in inputs {
+            let mut pos_in = 0;
+            while pos_in < input.len() {
+                let (code, s_len, _) = self.symbol_table.encode_for(&Symbol::from_bytes(&input[pos_in..]));
+                counts[code as usize] += 1;
+                pos_in += s_len;
+            }
+        }
+        counts
+    }
+
+    /// count the input bytes that are encoded as part of a symbol longer than one byte, the rest are
+    /// single byte symbols or escapes
+    pub fn covered_bytes(&self, input: &[u8]) -> usize {
+        let (mut pos_in, mut covered) = (0, 0);
+        while pos_in < input.len() {
+            let (_, s_len, _) = self.symbol_table.encode_for(&Symbol::from_bytes(&input[pos_in..]));
+            if s_len > 1 {
+                covered += s_len;
+            }
+            pos_in += s_len;
+        }
+        covered
+    }
+
+    /// encode a record followed by the end of record marker, the first code without a symbol, so
+    /// records appended to one stream can be split again by `Decoder::decode_records` and a damaged
+    /// record does not take the records after its marker with it
+    /// fails with `NoMarkerCode` if every code has a symbol, see `SymbolTableBuilder::record_marker`
+    pub fn encode_with_marker(&self, input: &[u8]) -> Result<Vec<u8>, EncodeError> {
+        let symbol_table = self.symbol_table;
+        if symbol_table.is_identity() {
+            return Err(EncodeError::NoMarkerCode);
+        }
+        let marker_code = if symbol_table.is_wide() { CODE_BASE as usize } else { 0 } + symbol_table.len();
+        let mut buf = self.encode_bytes(input);
+        if symbol_table.is_wide() {
+            if marker_code >= CODE_MAX_WIDE as usize {
+                return Err(EncodeError::NoMarkerCode);
+            }
+            buf.extend_from_slice(&(marker_code as u16).to_le_bytes());
+        } else {
+            if marker_code >= CODE_ESCAPE as usize {
+                return Err(EncodeError::NoMarkerCode);
+            }
+            buf.push(code_byte(marker_code as u16, symbol_table.escape_code()));
+        }
+        Ok(buf)
+    }
+
+    /// the longest `encode_with_header` output with the table included, the dump of this table
+    /// followed by the longest encoding of `input_len` bytes
+    pub fn max_encoded_len_with_table(&self, input_len: usize) -> usize {
+        self.symbol_table.dump_len() + Encoder::max_encoded_len(input_len)
+    }
+
+    /// encode a string into a self-describing block, | symbol table dump | encoding length | encoding |
+    /// where the length is a varint, unlike `encode(.., true)` the blocks can be concatenated and read
+    /// back one after another with `Decoder::decode_self_describing`
+    pub fn encode_self_describing(&self, str: &str) -> Vec<u8> {
+        self.encode_bytes_self_describing(str.as_bytes())
+    }
+
+    /// encode raw bytes into a self-describing block like `encode_self_describing`
+    pub fn encode_bytes_self_describing(&self, input: &[u8]) -> Vec<u8> {
+        let encoding = self.encode_bytes(input);
+        let mut buf = Vec::with_capacity(self.symbol_table.dump_len() + encoding.len() + 4);
+        self.symbol_table.dump_into(&mut buf);
+        write_varint(&mut buf, encoding.len() as u64);
+        buf.extend_from_slice(&encoding);
         buf
     }
 
     pub fn encode(&self, str: &str, include_table: bool) -> Vec<u8> {
-        let mut buf = self.encode_str(str);
+        self.encode_with_header(str.as_bytes(), include_table)
+    }
+
+    /// encode raw bytes like `encode`, with the symbol table dump in front of the encoding if
+    /// `include_table` is true, i.e., | symbol table bytes | encoding bytes |
+    /// the buffer is allocated once with room for the dump and the longest encoding
+    pub fn encode_with_header(&self, input: &[u8], include_table: bool) -> Vec<u8> {
+        let table_len = if include_table { self.symbol_table.dump_len() } else { 0 };
+        let mut buf = Vec::with_capacity(table_len + Encoder::max_encoded_len(input.len()));
         if include_table {
-            let mut table_buf = self.symbol_table.dump();
-            let mut buf_with_tab = Vec::with_capacity(table_buf.len() + table_buf.len());
-            buf_with_tab.append(&mut table_buf);
-            buf_with_tab.append(&mut buf);
-            buf_with_tab
-        } else {
-            buf
+            self.symbol_table.dump_into(&mut buf);
         }
+        self.encode_counting_escapes(input, &mut buf);
+        buf
+    }
+
+    /// encode raw bytes behind the symbol table dump like `encode_with_header`, with the length of
+    /// the dump in front as a varint, i.e., | dump length | symbol table bytes | encoding bytes |
+    /// so `Decoder::decode_framed` finds the encoding even for table formats it can not delimit
+    pub fn encode_framed(&self, input: &[u8]) -> Vec<u8> {
+        let table_len = self.symbol_table.dump_len();
+        let mut buf = Vec::with_capacity(MAX_VARINT_LEN + table_len + Encoder::max_encoded_len(input.len()));
+        write_varint(&mut buf, table_len as u64);
+        self.symbol_table.dump_into(&mut buf);
+        self.encode_counting_escapes(input, &mut buf);
+        buf
     }
 }
 
+/// the byte written for a code of a table with one-byte codes
+/// the table escapes with `CODE_ESCAPE`, the codes from a custom escape code on are shifted up by one
+/// so that byte is left for the escapes
+pub(crate) fn code_byte(code: u16, escape: u8) -> u8 {
+    if code == CODE_ESCAPE as u16 {
+        escape
+    } else if code >= escape as u16 {
+        code as u8 + 1
+    } else {
+        code as u8
+    }
+}
+
+/// the symbols of a table with one-byte codes are indexed by the code byte, see `code_byte`
+#[derive(Clone)]
 pub struct Decoder {
-    symbols: [u64; CODE_ESCAPE as usize],
-    lens: [u8; CODE_ESCAPE as usize],
+    symbols: Vec<u64>,
+    lens: Vec<u8>,
+    wide: bool,
+    identity: bool,
+    escape: u8,
 }
 
 impl Decoder {
+    #[allow(clippy::borrowed_box)]
     pub fn from_table(table: &Box<dyn SymbolTable>) -> Decoder {
-        let mut symbols = [0u64; CODE_ESCAPE as usize];
-        let mut lens = [0u8; CODE_ESCAPE as usize];
-        for i in 0..table.len() {
-            let s = table.get_symbol(i as u16);
-            symbols[i] = s.as_u64();
-            lens[i] = s.length() as u8;
-        }
-        Decoder { symbols, lens }
-    }
-
-    pub fn from_table_bytes(buf: &Vec<u8>) -> (usize, Decoder) {
-        let mut symbols = [0u64; CODE_ESCAPE as usize];
-        let mut lens = [0u8; CODE_ESCAPE as usize];
-        let encode_endian = Endian::from_u8(*buf.get(0).unwrap());
-        let len_histo = &buf[1..9];
-        let (mut pos, mut code) = (9, 0usize);
-        for len in 1..=Symbol::MAX_LEN {
-            for _ in 0..len_histo[len - 1] {
-                let mut num = 0u64;
-                if Endian::get_native_endian() != encode_endian {
-                    num |= *buf.get(pos).unwrap() as u64;
-                    for i in 1..len {
-                        num <<= 8;
-                        num |= *buf.get(pos + i).unwrap() as u64;
-                    }
-                } else {
-                    num |= *buf.get(pos + len - 1).unwrap() as u64;
-                    for i in (0..len - 1).rev() {
-                        num <<= 8;
-                        num |= *buf.get(pos + i).unwrap() as u64;
-                    }
+        Self::from_table_ref(table.as_ref())
+    }
+
+    /// build a decoder from a table borrowed directly, which needs no `Box`
+    pub fn from_table_ref<T: SymbolTable + ?Sized>(table: &T) -> Decoder {
+        if table.is_identity() {
+            return Self::identity();
+        }
+        let first_code = if table.is_wide() { CODE_BASE } else { 0 };
+        let table_symbols = (0..table.len())
+            .map(|i| *table.get_symbol(first_code + i as u16))
+            .collect::<Vec<Symbol>>();
+        Self::from_symbols(&table_symbols, table.is_wide(), table.escape_code())
+    }
+
+    /// build a decoder from a dumped symbol table
+    /// returns the end position of the table in the buffer together with the decoder
+    pub fn from_table_bytes(buf: &[u8]) -> Result<(usize, Decoder), DecodeError> {
+        let dumped = load_symbols(buf)?;
+        let decoder = if dumped.identity {
+            Self::identity()
+        } else {
+            Self::from_symbols(&dumped.symbols, dumped.wide, dumped.escape)
+        };
+        Ok((dumped.end_pos, decoder))
+    }
+
+    /// decode the first block written by `Encoder::encode_self_describing` in the buffer
+    /// returns the number of bytes the block takes together with the string, so a buffer of
+    /// back-to-back blocks can be decoded by advancing past every block in turn
+    pub fn decode_self_describing(buf: &[u8]) -> Result<(usize, String), DecodeError> {
+        let (end, decoded) = Self::decode_bytes_self_describing(buf)?;
+        Ok((end, String::from_utf8_lossy(&decoded).into_owned()))
+    }
+
+    /// decode the first self-describing block in the buffer to raw bytes like
+    /// `decode_self_describing`
+    pub fn decode_bytes_self_describing(buf: &[u8]) -> Result<(usize, Vec<u8>), DecodeError> {
+        let dumped = load_symbols(buf)?;
+        let mut pos = dumped.end_pos;
+        let decoder = if dumped.identity {
+            Self::identity()
+        } else {
+            Self::from_symbols(&dumped.symbols, dumped.wide, dumped.escape)
+        };
+        let len = read_varint(buf, &mut pos)? as usize;
+        let end = pos.checked_add(len).ok_or(DecodeError::TooShort)?;
+        let decoded = decoder.decode_checked(buf.get(pos..end).ok_or(DecodeError::TooShort)?)?;
+        Ok((end, decoded))
+    }
+
+    /// decode a message written by `Encoder::encode_framed`, the encoding starts right after the
+    /// framed table length, whatever part of the table the dump parser reads
+    /// the encoding is validated like in `decode_checked`
+    pub fn decode_framed(buf: &[u8]) -> Result<Vec<u8>, DecodeError> {
+        let mut pos = 0;
+        let table_len = read_varint(buf, &mut pos)? as usize;
+        let table_end = pos.checked_add(table_len).ok_or(DecodeError::TooShort)?;
+        let (_, decoder) = Self::from_table_bytes(buf.get(pos..table_end).ok_or(DecodeError::TooShort)?)?;
+        decoder.decode_checked(&buf[table_end..])
+    }
+
+    /// decoder of the identity table, which returns the encodings unchanged
+    fn identity() -> Decoder {
+        Decoder { symbols: Vec::new(), lens: Vec::new(), wide: false, identity: true, escape: CODE_ESCAPE }
+    }
+
+    /// the learned symbols of a wide table follow the codes of the single bytes
+    fn from_symbols(table_symbols: &[Symbol], wide: bool, escape: u8) -> Decoder {
+        let (size, first_code) = if wide { (CODE_MAX_WIDE, CODE_BASE) } else { (CODE_BASE, 0) };
+        let mut symbols = vec![0u64; size as usize];
+        let mut lens = vec![0u8; size as usize];
+        if wide {
+            for i in 0..CODE_BASE as usize {
+                symbols[i] = i as u64;
+                lens[i] = 1;
+            }
+        }
+        for (i, s) in table_symbols.iter().enumerate() {
+            let code = if wide { (first_code as usize + i) as u16 } else { code_byte(i as u16, escape) as u16 };
+            symbols[code as usize] = s.as_u64();
+            lens[code as usize] = s.length() as u8;
+        }
+        Decoder { symbols, lens, wide, identity: false, escape }
+    }
+
+    /// the longest decoding of an encoding of `encoded_len` bytes, which is a symbol of the longest
+    /// length for every byte, so a buffer of this size never grows in `decode_into`
+    pub fn max_decoded_len(encoded_len: usize) -> usize {
+        encoded_len * Symbol::MAX_LEN
+    }
+
+    /// whether the decoder reads two-byte codes
+    pub fn is_wide(&self) -> bool {
+        self.wide
+    }
+
+    /// whether the encodings are the input stored verbatim
+    pub fn is_identity(&self) -> bool {
+        self.identity
+    }
+
+    /// the byte preceding an escaped byte, 255 unless the table was built with another one
+    pub fn escape_code(&self) -> u8 {
+        self.escape
+    }
+
+    /// safe decode method, the bytes are decoded first and converted to a string once, so invalid
+    /// UTF-8 is replaced by U+FFFD instead of splitting characters across symbols
+    /// an escape code at the end of the buffer without its escaped byte ends the stream like it does
+    /// in `decode`
+    #[allow(clippy::borrowed_box)]
+    pub fn decode_with_tab(table: &Box<dyn SymbolTable>, buf: &[u8]) -> String {
+        if table.is_identity() {
+            return String::from_utf8_lossy(buf).into_owned();
+        }
+        let mut decode_buf = Vec::with_capacity(buf.len() * 4);
+        let push_symbol = |decode_buf: &mut Vec<u8>, code: u16| {
+            let (bytes, len) = table.get_symbol(code).to_bytes();
+            decode_buf.extend_from_slice(&bytes[..len]);
+        };
+        if table.is_wide() {
+            for code in buf.chunks_exact(2) {
+                push_symbol(&mut decode_buf, u16::from_le_bytes([code[0], code[1]]) & CODE_MASK_WIDE);
+            }
+        } else {
+            let escape = table.escape_code();
+            let mut pos = 0;
+            while pos < buf.len() {
+                let b = buf[pos];
+                pos += 1;
+                if b != escape {
+                    push_symbol(&mut decode_buf, if b > escape { b - 1 } else { b } as u16);
+                    continue;
+                }
+                let Some(&escaped) = buf.get(pos) else {
+                    break;
+                };
+                decode_buf.push(escaped);
+                pos += 1;
+            }
+        }
+        String::from_utf8_lossy(&decode_buf).into_owned()
+    }
+
+    /// decode method that uses the unsafe method
+    pub fn decode(&self, str_buf: &[u8]) -> String {
+        unsafe { String::from_utf8_unchecked(self.decode_bytes(str_buf)) }
+    }
+
+    /// decode to raw bytes, which is lossless for encodings of arbitrary (non UTF-8) bytes
+    pub fn decode_bytes(&self, encoding: &[u8]) -> Vec<u8> {
+        if let Some(symbol) = self.single_symbol(encoding) {
+            return symbol;
+        }
+        let mut decode_buf = Vec::new();
+        self.decode_into(encoding, &mut decode_buf);
+        decode_buf
+    }
+
+    /// decode like `decode`, but check that the output is valid UTF-8 instead of assuming it, for
+    /// encodings that may come from arbitrary bytes
+    pub fn decode_utf8(&self, encoding: &[u8]) -> Result<String, FromUtf8Error> {
+        String::from_utf8(self.decode_bytes(encoding))
+    }
+
+    /// safe decode method for untrusted encodings, every code and escape is validated
+    /// it returns `InvalidCode` for a code not in the table, `TrailingEscape` if the encoding ends
+    /// with an escape code and `TooShort` if it ends in the middle of a two-byte code
+    pub fn decode_checked(&self, encoding: &[u8]) -> Result<Vec<u8>, DecodeError> {
+        self.decode_limited(encoding, usize::MAX)
+    }
+
+    /// decode an untrusted encoding like `decode_checked`, but stop with `OutputTooLarge` as soon as
+    /// the output would grow past `max_output` bytes, so a crafted encoding can not exhaust memory
+    pub fn decode_limited(&self, encoding: &[u8], max_output: usize) -> Result<Vec<u8>, DecodeError> {
+        if self.identity {
+            if encoding.len() > max_output {
+                return Err(DecodeError::OutputTooLarge);
+            }
+            return Ok(encoding.to_vec());
+        }
+        let mut decode_buf = Vec::with_capacity(min(encoding.len() * 4, max_output));
+        let mut pos = 0;
+        while pos < encoding.len() {
+            let code = if self.wide {
+                let bytes = encoding.get(pos..pos + 2).ok_or(DecodeError::TooShort)?;
+                pos += 2;
+                u16::from_le_bytes([bytes[0], bytes[1]])
+            } else if encoding[pos] == self.escape {
+                let escaped = *encoding.get(pos + 1).ok_or(DecodeError::TrailingEscape)?;
+                if decode_buf.len() >= max_output {
+                    return Err(DecodeError::OutputTooLarge);
                 }
-                symbols[code] = num;
-                lens[code] = len as u8;
-                code += 1;
-                pos += len;
+                decode_buf.push(escaped);
+                pos += 2;
+                continue;
+            } else {
+                pos += 1;
+                encoding[pos - 1] as u16
+            };
+            let len = *self.lens.get(code as usize).ok_or(DecodeError::InvalidCode(code))? as usize;
+            if len == 0 {
+                return Err(DecodeError::InvalidCode(code));
             }
+            if len > max_output - decode_buf.len() {
+                return Err(DecodeError::OutputTooLarge);
+            }
+            decode_buf.extend_from_slice(&self.symbols[code as usize].to_ne_bytes()[..len]);
+        }
+        Ok(decode_buf)
+    }
+
+    /// decode only the first `n` bytes of the output, or all of it if it is shorter, so a prefix
+    /// predicate can be checked without decoding the whole record
+    pub fn decoded_prefix(&self, encoding: &[u8], n: usize) -> Vec<u8> {
+        let mut decode_buf = self.decode_prefix_consumed(encoding, n).0;
+        decode_buf.truncate(n);
+        decode_buf
+    }
+
+    /// decode one record of `record_len_bytes` decoded bytes from the front of a buffer of
+    /// concatenated encodings, and return it with the number of code bytes it took, which is where
+    /// the next record starts
+    /// returns `TooShort` if the buffer decodes to fewer bytes, and `InvalidRecordLength` if the
+    /// record would end inside a symbol or the record is not valid UTF-8, both of which mean the
+    /// length does not belong to this record
+    pub fn decode_one(&self, buf: &[u8], record_len_bytes: usize) -> Result<(String, usize), DecodeError> {
+        let (decode_buf, consumed) = self.decode_prefix_consumed(buf, record_len_bytes);
+        if decode_buf.len() < record_len_bytes {
+            return Err(DecodeError::TooShort);
+        }
+        if decode_buf.len() > record_len_bytes {
+            return Err(DecodeError::InvalidRecordLength);
         }
-        (pos, Decoder { symbols, lens })
+        let record = String::from_utf8(decode_buf).map_err(|_| DecodeError::InvalidRecordLength)?;
+        Ok((record, consumed))
     }
 
-    /// safe decode method
-    pub fn decode_with_tab(table: &Box<dyn SymbolTable>, buf: &Vec<u8>) -> String {
-        let mut str = String::with_capacity(buf.len() * 4);
+    /// decode at least the first `n` bytes of the output, or all of it if it is shorter, and count
+    /// the encoding bytes that were read, the last symbol is not cut at `n`
+    fn decode_prefix_consumed(&self, encoding: &[u8], n: usize) -> (Vec<u8>, usize) {
+        if self.identity {
+            let end = min(n, encoding.len());
+            return (encoding[..end].to_vec(), end);
+        }
+        let mut decode_buf = Vec::with_capacity(n + Symbol::MAX_LEN);
         let mut pos = 0;
-        while pos < buf.len() {
-            let b = buf.get(pos).unwrap();
-            pos += 1;
-            if *b == 255 {
-                str.push(*buf.get(pos).unwrap() as char);
+        while pos < encoding.len() && decode_buf.len() < n {
+            let code = if self.wide {
+                let Some(bytes) = encoding.get(pos..pos + 2) else {
+                    // a trailing odd byte is not a complete code
+                    break;
+                };
+                pos += 2;
+                (u16::from_le_bytes([bytes[0], bytes[1]]) & CODE_MASK_WIDE) as usize
+            } else if encoding[pos] == self.escape {
+                let Some(&escaped) = encoding.get(pos + 1) else {
+                    break;
+                };
+                decode_buf.push(escaped);
+                pos += 2;
+                continue;
+            } else {
                 pos += 1;
+                encoding[pos - 1] as usize
+            };
+            decode_buf.extend_from_slice(&self.symbols[code].to_ne_bytes()[..self.lens[code] as usize]);
+        }
+        (decode_buf, pos)
+    }
+
+    /// decode the records of a stream written by `Encoder::encode_with_marker`, split at the end of
+    /// record markers, a damaged record decodes to wrong bytes but the records after its marker
+    /// decode as before
+    /// the bytes after the last marker are an unfinished record and are left out
+    pub fn decode_records(&self, buf: &[u8]) -> Vec<Vec<u8>> {
+        let mut records = Vec::new();
+        let Some(marker_code) = self.marker_code() else {
+            return records;
+        };
+        let code_len = if self.wide { 2 } else { 1 };
+        let (mut start, mut pos) = (0, 0);
+        while pos + code_len <= buf.len() {
+            let code = if self.wide {
+                (u16::from_le_bytes([buf[pos], buf[pos + 1]]) & CODE_MASK_WIDE) as usize
             } else {
-                str.push_str(&table.get_symbol(*b as u16).to_string());
+                buf[pos] as usize
+            };
+            if code == marker_code {
+                records.push(self.decode_bytes(&buf[start..pos]));
+                start = pos + code_len;
+            } else if !self.wide && code == self.escape as usize {
+                // the escaped byte is a literal, even if it equals the marker
+                pos += 1;
             }
+            pos += code_len;
         }
-        str
+        records
     }
 
-    /// decode method that uses the unsafe method
-    pub fn decode(&self, str_buf: &Vec<u8>) -> String {
+    /// decode into the given buffer, which is cleared first and only grows when its capacity is
+    /// not enough, so the allocation can be reused across calls
+    /// returns the decoded length
+    pub fn decode_into(&self, encoding: &[u8], decode_buf: &mut Vec<u8>) -> usize {
+        decode_buf.clear();
+        self.decode_bytes_append(encoding, decode_buf)
+    }
+
+    /// decode to the end of the given string, keeping what it already holds, so one string can be
+    /// reused for many records
+    /// the encoding must come from a valid UTF-8 input like for `decode`
+    /// returns the decoded length
+    pub fn decode_append(&self, encoding: &[u8], out: &mut String) -> usize {
+        unsafe { self.decode_bytes_append(encoding, out.as_mut_vec()) }
+    }
+
+    /// decode every record into one contiguous arena, which avoids an allocation per record
+    /// returns the arena together with the range of every record in it
+    pub fn decode_all_into_arena(&self, encodings: &[Vec<u8>]) -> (Vec<u8>, Vec<Range<usize>>) {
+        let mut arena = Vec::with_capacity(encodings.iter().map(|e| e.len()).sum::<usize>() * 4);
+        let mut ranges = Vec::with_capacity(encodings.len());
+        for encoding in encodings {
+            let start = arena.len();
+            let len = self.decode_bytes_append(encoding, &mut arena);
+            ranges.push(start..start + len);
+        }
+        (arena, ranges)
+    }
+
+    /// decode every record and join them with the separator into one string, without a `String`
+    /// per record
+    pub fn decode_concat(&self, encodings: &[Vec<u8>], sep: &str) -> String {
+        let mut decode_buf = Vec::new();
+        for (i, encoding) in encodings.iter().enumerate() {
+            if i > 0 {
+                decode_buf.extend_from_slice(sep.as_bytes());
+            }
+            self.decode_bytes_append(encoding, &mut decode_buf);
+        }
+        unsafe { String::from_utf8_unchecked(decode_buf) }
+    }
+
+    /// decode the records lazily, one `decode` per record as the iterator is advanced, so a scan
+    /// that stops early does not decode the rest like `decode_all_strings` does
+    pub fn decode_iter<'a>(&'a self, encodings: &'a [Vec<u8>]) -> impl Iterator<Item = String> + 'a {
+        encodings.iter().map(move |encoding| self.decode(encoding))
+    }
+
+    /// decode to the end of the given buffer, keeping what it already holds
+    /// returns the decoded length
+    fn decode_bytes_append(&self, encoding: &[u8], decode_buf: &mut Vec<u8>) -> usize {
+        if self.identity {
+            decode_buf.extend_from_slice(encoding);
+            return encoding.len();
+        }
+        if self.wide {
+            return self.decode_wide_append(encoding, decode_buf);
+        }
         let (mut pos_in, mut pos_out) = (0, 0);
-        let mut decode_buf = vec![0u8; str_buf.len() * Symbol::MAX_LEN];
+        let start = decode_buf.len();
+        // turns the escape code into 0xFF, which the escape mask looks for
+        let escape_xor = u32::from_ne_bytes([!self.escape; 4]);
+        // every code byte decodes to at most `Symbol::MAX_LEN` bytes and an escape pair to one, so
+        // `pos_out <= pos_in * MAX_LEN` holds throughout and the full width store of the code at
+        // `pos_in` ends by `(pos_in + 1) * MAX_LEN <= max_decoded_len`, within the reservation
+        decode_buf.reserve(Self::max_decoded_len(encoding.len()));
         unsafe {
-            let out = decode_buf.as_mut_ptr();
-            while pos_in + 4 < str_buf.len() {
-                let next_block = bulk_load_u32(&str_buf[pos_in..pos_in + 4]);
+            let out = decode_buf.as_mut_ptr().add(start);
+            while pos_in + 4 < encoding.len() {
+                let next_block = bulk_load_u32(&encoding[pos_in..pos_in + 4]) ^ escape_xor;
                 let escape_mask = (next_block & 0x80808080) & ((((!next_block) & 0x7F7F7F7F) + 0x7F7F7F7F) ^ 0x80808080);
                 if escape_mask == 0 {
-                    self.unaligned_store(&mut pos_in, &mut pos_out, &str_buf, out);
-                    self.unaligned_store(&mut pos_in, &mut pos_out, &str_buf, out);
-                    self.unaligned_store(&mut pos_in, &mut pos_out, &str_buf, out);
-                    self.unaligned_store(&mut pos_in, &mut pos_out, &str_buf, out);
+                    self.unaligned_store(&mut pos_in, &mut pos_out, encoding, out);
+                    self.unaligned_store(&mut pos_in, &mut pos_out, encoding, out);
+                    self.unaligned_store(&mut pos_in, &mut pos_out, encoding, out);
+                    self.unaligned_store(&mut pos_in, &mut pos_out, encoding, out);
                 } else {
                     let mut first_escape_pos = escape_mask.trailing_zeros() >> 3;
                     while first_escape_pos > 0 {
-                        self.unaligned_store(&mut pos_in, &mut pos_out, &str_buf, out);
+                        self.unaligned_store(&mut pos_in, &mut pos_out, encoding, out);
                         first_escape_pos -= 1;
                     }
-                    decode_buf[pos_out] = str_buf[pos_in + 1];
+                    debug_assert!(pos_out < Self::max_decoded_len(encoding.len()));
+                    *out.add(pos_out) = encoding[pos_in + 1];
                     pos_in += 2;
                     pos_out += 1;
                 }
             }
-            while pos_in < str_buf.len() {
-                if str_buf[pos_in] != CODE_ESCAPE {
-                    self.unaligned_store(&mut pos_in, &mut pos_out, &str_buf, out);
-                } else {
-                    decode_buf[pos_out] = str_buf[pos_in + 1];
-                    pos_in += 2;
-                    pos_out += 1;
+            // the last 1 to 4 bytes, which is the whole encoding of most short strings, are loaded as
+            // one zero padded block whose padding is masked out of the escape mask
+            while pos_in < encoding.len() {
+                let rest = encoding.len() - pos_in;
+                let next_block = bulk_load_u32(&encoding[pos_in..]) ^ escape_xor;
+                let valid_mask = u32::MAX >> ((4 - rest) << 3);
+                let escape_mask = (next_block & 0x80808080) & ((((!next_block) & 0x7F7F7F7F) + 0x7F7F7F7F) ^ 0x80808080) & valid_mask;
+                if escape_mask == 0 {
+                    for _ in 0..rest {
+                        self.unaligned_store(&mut pos_in, &mut pos_out, encoding, out);
+                    }
+                    break;
+                }
+                let mut first_escape_pos = escape_mask.trailing_zeros() >> 3;
+                while first_escape_pos > 0 {
+                    self.unaligned_store(&mut pos_in, &mut pos_out, encoding, out);
+                    first_escape_pos -= 1;
                 }
+                if pos_in + 1 == encoding.len() {
+                    // a lone escape code without the escaped byte, treat it as the end of the stream
+                    break;
+                }
+                debug_assert!(pos_out < Self::max_decoded_len(encoding.len()));
+                *out.add(pos_out) = encoding[pos_in + 1];
+                pos_in += 2;
+                pos_out += 1;
+            }
+            decode_buf.set_len(start + pos_out);
+        }
+        pos_out
+    }
+
+    /// a trailing odd byte is not a complete code and is dropped
+    fn decode_wide_append(&self, encoding: &[u8], decode_buf: &mut Vec<u8>) -> usize {
+        let mut pos_out = 0;
+        let start = decode_buf.len();
+        // the i-th code starts at most at `i * MAX_LEN`, so its full width store ends within the
+        // `MAX_LEN` bytes reserved for every complete code
+        let reserved = (encoding.len() >> 1) * Symbol::MAX_LEN;
+        decode_buf.reserve(reserved);
+        unsafe {
+            let out = decode_buf.as_mut_ptr().add(start);
+            for code in encoding.chunks_exact(2) {
+                let code = (u16::from_le_bytes([code[0], code[1]]) & CODE_MASK_WIDE) as usize;
+                debug_assert!(pos_out + U64_SIZE <= reserved);
+                core::ptr::copy_nonoverlapping(self.symbols[code].to_ne_bytes().as_ptr(), out.add(pos_out), U64_SIZE);
+                pos_out += self.lens[code] as usize;
             }
-            decode_buf.truncate(pos_out);
-            String::from_utf8_unchecked(decode_buf)
+            decode_buf.set_len(start + pos_out);
         }
+        pos_out
     }
 
+    /// writes all `U64_SIZE` bytes of the symbol and advances `pos_out` by its length only, the
+    /// caller must have `max_decoded_len(str_in.len())` bytes reserved at `out`
     #[inline(always)]
-    unsafe fn unaligned_store(&self, pos_in: &mut usize, pos_out: &mut usize, str_in: &Vec<u8>, out: *mut u8) {
+    unsafe fn unaligned_store(&self, pos_in: &mut usize, pos_out: &mut usize, str_in: &[u8], out: *mut u8) {
         let code = str_in[*pos_in] as usize;
-        std::ptr::copy_nonoverlapping(self.symbols[code].to_ne_bytes().as_ptr(), out.add(*pos_out), U64_SIZE);
+        debug_assert!(*pos_out + U64_SIZE <= Self::max_decoded_len(str_in.len()));
+        core::ptr::copy_nonoverlapping(self.symbols[code].to_ne_bytes().as_ptr(), out.add(*pos_out), U64_SIZE);
         *pos_in += 1;
         *pos_out += self.lens[code] as usize;
     }
 }
 
+/// lists the learned symbols in code order, the same way the symbol tables do
+impl Display for Decoder {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        let symbols_str = &self.learned_codes()
+            .map(|code| String::from_utf8_lossy(&self.symbols[code].to_ne_bytes()[..self.lens[code] as usize]).into_owned())
+            .collect::<Vec<String>>()
+            .join(", ");
+        write!(f, "[{}]", symbols_str)
+    }
+}
+
+/// prints every learned code with the bytes of its symbol, non-printable bytes are escaped
+impl Debug for Decoder {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Decoder")
+            .field("wide", &self.wide)
+            .field("identity", &self.identity)
+            .field("escape", &self.escape)
+            .field("symbols", &DebugSymbols(self))
+            .finish()
+    }
+}
+
+impl Decoder {
+    /// the codes with a symbol, the codes of the single bytes of a wide table are left out as
+    /// every wide table has them
+    fn learned_codes(&self) -> impl Iterator<Item = usize> + '_ {
+        let first_code = if self.wide { CODE_BASE as usize } else { 0 };
+        (first_code..self.lens.len()).filter(|&code| self.lens[code] > 0)
+    }
+
+    /// the end of record marker of `Encoder::encode_with_marker`, the first code without a symbol,
+    /// with one-byte codes it is the byte written for that code
+    fn marker_code(&self) -> Option<usize> {
+        if self.identity {
+            return None;
+        }
+        let first_code = if self.wide { CODE_BASE as usize } else { 0 };
+        (first_code..self.lens.len()).find(|&code| self.lens[code] == 0 && (self.wide || code != self.escape as usize))
+    }
+
+    /// the symbol of an encoding that is one code without escapes, such as a value that is one
+    /// learned symbol, copied out without the setup of the decode loop
+    fn single_symbol(&self, encoding: &[u8]) -> Option<Vec<u8>> {
+        let code = match (self.identity, self.wide, encoding) {
+            (false, false, &[code]) if code != self.escape => code as usize,
+            (false, true, &[low, high]) => (u16::from_le_bytes([low, high]) & CODE_MASK_WIDE) as usize,
+            _ => return None,
+        };
+        Some(self.symbols[code].to_ne_bytes()[..self.lens[code] as usize].to_vec())
+    }
+}
+
+struct DebugSymbols<'a>(&'a Decoder);
+
+impl Debug for DebugSymbols<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        let decoder = self.0;
+        f.debug_map()
+            .entries(decoder.learned_codes().map(|code| {
+                (code, SymbolBytes(decoder.symbols[code].to_ne_bytes(), decoder.lens[code] as usize))
+            }))
+            .finish()
+    }
+}
+
+/// the bytes of a symbol written as a byte string literal
+struct SymbolBytes([u8; U64_SIZE], usize);
+
+impl Debug for SymbolBytes {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "b\"{}\"", self.0[..self.1].escape_ascii())
+    }
+}
+
 #[cfg(test)]
 mod test {
+    use crate::core::CODE_ESCAPE;
     use crate::core::codec::{Decoder, Encoder};
-    use crate::core::symbol_table::SymbolTableBuilder;
+    use crate::core::error::{DecodeError, EncodeError};
+    use crate::core::symbol_table::{PerfectHashSymbolTable, SymbolTable, SymbolTableBuilder};
 
     #[test]
     pub fn test_decode_with_dump_table() {
@@ -165,10 +870,849 @@ mod test {
         let symbol_table = SymbolTableBuilder::build_from(test_str);
         let encoder = Encoder::from_table(&symbol_table);
         let buf = symbol_table.dump();
-        let (table_end_pos, decoder) = Decoder::from_table_bytes(&buf);
+        let (table_end_pos, decoder) = Decoder::from_table_bytes(&buf).unwrap();
         assert_eq!(buf.len(), table_end_pos);
         let encode_buf = encoder.encode(test_str, false);
         let decode_str = decoder.decode(&encode_buf);
         assert_eq!(test_str, decode_str);
     }
-}
\ No newline at end of file
+
+    #[test]
+    pub fn test_from_invalid_table_bytes() {
+        let symbol_table = SymbolTableBuilder::build_from("tumcwitumvldb");
+        let buf = symbol_table.dump();
+        assert_eq!(DecodeError::TooShort, Decoder::from_table_bytes(&buf[..10]).err().unwrap());
+        assert_eq!(DecodeError::TooShort, Decoder::from_table_bytes(&buf[..buf.len() - 1]).err().unwrap());
+        let mut bad_magic = buf.clone();
+        bad_magic[0] = b'X';
+        assert_eq!(DecodeError::BadMagic, Decoder::from_table_bytes(&bad_magic).err().unwrap());
+        let mut bad_version = buf.clone();
+        bad_version[4] = 0xff;
+        assert_eq!(DecodeError::UnsupportedVersion(0xff), Decoder::from_table_bytes(&bad_version).err().unwrap());
+        let mut bad_flags = buf.clone();
+        bad_flags[5] = 0x80;
+        assert_eq!(DecodeError::UnknownFlags(0x80), Decoder::from_table_bytes(&bad_flags).err().unwrap());
+        let mut bad_endian = buf.clone();
+        bad_endian[6] = 2;
+        assert_eq!(DecodeError::UnknownEndian(2), Decoder::from_table_bytes(&bad_endian).err().unwrap());
+        // the histogram follows the endian byte and declares more than 255 symbols
+        let mut bad_histo = buf.clone();
+        bad_histo[7..9].copy_from_slice(&[200, 100]);
+        let declared = bad_histo[7..15].iter().map(|&n| n as usize).sum::<usize>();
+        assert_eq!(DecodeError::TooManySymbols(declared), Decoder::from_table_bytes(&bad_histo).err().unwrap());
+        let wide_buf = SymbolTableBuilder::new().wide_codes(true).train_from("tumcwitumvldb").dump();
+        let mut bad_wide_histo = wide_buf.clone();
+        bad_wide_histo[7..9].copy_from_slice(&4000u16.to_le_bytes());
+        assert!(matches!(Decoder::from_table_bytes(&bad_wide_histo), Err(DecodeError::TooManySymbols(_))));
+    }
+
+    #[test]
+    pub fn test_from_v1_table_bytes() {
+        let test_str = "tumcwitumvldb";
+        let symbol_table = SymbolTableBuilder::build_from(test_str);
+        let mut buf = symbol_table.dump();
+        // version 1 has no flags byte
+        buf[4] = 1;
+        buf.remove(5);
+        let (table_end_pos, decoder) = Decoder::from_table_bytes(&buf).unwrap();
+        assert_eq!(buf.len(), table_end_pos);
+        let encoder = Encoder::from_table(&symbol_table);
+        assert_eq!(test_str, decoder.decode(&encoder.encode_str(test_str)));
+    }
+
+    #[test]
+    pub fn test_wide_codes() {
+        let lines = crate::read_string_lines("assets/test_data/l_comment").unwrap();
+        let mut builder = SymbolTableBuilder::new().wide_codes(true);
+        let symbol_table = builder.train_from_samples(&crate::core::take_sample(&lines));
+        assert!(symbol_table.is_wide());
+        assert!(symbol_table.len() > 255);
+        let encoder = Encoder::from_table(&symbol_table);
+        let decoder = Decoder::from_table(&symbol_table);
+        let buf = symbol_table.dump();
+        let (table_end_pos, loaded_decoder) = Decoder::from_table_bytes(&buf).unwrap();
+        assert_eq!(buf.len(), table_end_pos);
+        assert!(loaded_decoder.is_wide());
+        for line in lines.iter().take(1000) {
+            let encoding = encoder.encode_str(line);
+            assert_eq!(0, encoding.len() % 2);
+            assert_eq!(*line, decoder.decode(&encoding));
+            assert_eq!(*line, loaded_decoder.decode(&encoding));
+            assert_eq!(*line, Decoder::decode_with_tab(&symbol_table, &encoding));
+        }
+        let invalid_utf8 = [0xc3u8, 0x28, 0xa0, 0xa1, 0xff];
+        assert_eq!(invalid_utf8.to_vec(), decoder.decode_bytes(&encoder.encode_bytes(&invalid_utf8)));
+    }
+
+    #[test]
+    pub fn test_from_table_ref() {
+        let test_str = "tumcwitumvldb";
+        let symbol_table = SymbolTableBuilder::build_from(test_str);
+        let encoder = Encoder::from_table_ref(symbol_table.as_ref());
+        assert_eq!(Encoder::from_table(&symbol_table).encode_str(test_str), encoder.encode_str(test_str));
+        let decoder = Decoder::from_table_ref(symbol_table.as_ref());
+        assert_eq!(test_str, decoder.decode(&encoder.encode_str(test_str)));
+    }
+
+    #[test]
+    pub fn test_encode_trailing_byte() {
+        let mut symbol_table = PerfectHashSymbolTable::new();
+        symbol_table.add_bytes(b"a\0");
+        symbol_table.add_bytes(b"b");
+        symbol_table.finalize();
+        let encoder = Encoder::from_table_ref(&symbol_table);
+        let decoder = Decoder::from_table_ref(&symbol_table);
+        // a trailing "a" must not match the two-byte symbol "a\0"
+        assert_eq!(b"ba".to_vec(), decoder.decode_bytes(&encoder.encode_bytes(b"ba")));
+        assert_eq!(b"ba\0".to_vec(), decoder.decode_bytes(&encoder.encode_bytes(b"ba\0")));
+        for b in 0..=255u8 {
+            let input = [b'b', b];
+            let encoding = encoder.encode_bytes(&input);
+            assert!(encoding.len() <= input.len() * 2);
+            assert_eq!(input.to_vec(), decoder.decode_bytes(&encoding));
+        }
+    }
+
+    #[test]
+    pub fn test_decode_checked() {
+        let test_str = "tumcwitumvldb";
+        let symbol_table = SymbolTableBuilder::build_from(test_str);
+        let encoder = Encoder::from_table(&symbol_table);
+        let decoder = Decoder::from_table(&symbol_table);
+        let invalid_utf8 = [0xc3u8, 0x28, b't', b'u', b'm', b'c', 0xa0, 0xa1, 0xff, b'b'];
+        let encoding = encoder.encode_bytes(&invalid_utf8);
+        assert_eq!(invalid_utf8.to_vec(), decoder.decode_checked(&encoding).unwrap());
+        assert_eq!(Ok(Vec::new()), decoder.decode_checked(&[]));
+        assert_eq!(Err(DecodeError::TrailingEscape), decoder.decode_checked(&[255]));
+        let unused_code = symbol_table.len() as u8;
+        assert_eq!(Err(DecodeError::InvalidCode(unused_code as u16)), decoder.decode_checked(&[0, unused_code]));
+
+        let wide_table = SymbolTableBuilder::new().wide_codes(true).train_from(test_str);
+        let encoder = Encoder::from_table(&wide_table);
+        let decoder = Decoder::from_table(&wide_table);
+        let encoding = encoder.encode_bytes(&invalid_utf8);
+        assert_eq!(invalid_utf8.to_vec(), decoder.decode_checked(&encoding).unwrap());
+        assert_eq!(Err(DecodeError::TooShort), decoder.decode_checked(&encoding[..encoding.len() - 1]));
+        assert_eq!(Err(DecodeError::InvalidCode(0xffff)), decoder.decode_checked(&[0xff, 0xff]));
+    }
+
+    #[test]
+    pub fn test_decode_limited() {
+        let test_str = "tumcwitumvldb";
+        let tables = [
+            SymbolTableBuilder::build_from(test_str),
+            SymbolTableBuilder::new().wide_codes(true).train_from(test_str),
+            SymbolTableBuilder::identity_table(),
+        ];
+        let input = b"tumcwitumvldb\xff tumc";
+        for symbol_table in &tables {
+            let encoder = Encoder::from_table(symbol_table);
+            let decoder = Decoder::from_table(symbol_table);
+            let encoding = encoder.encode_bytes(input);
+            assert_eq!(input.to_vec(), decoder.decode_limited(&encoding, input.len()).unwrap());
+            for max_output in [0, 1, 4, input.len() - 1] {
+                assert_eq!(Err(DecodeError::OutputTooLarge), decoder.decode_limited(&encoding, max_output));
+            }
+            assert_eq!(Ok(Vec::new()), decoder.decode_limited(&[], 0));
+        }
+        // the encoding is validated before the limit
+        let decoder = Decoder::from_table(&tables[0]);
+        assert_eq!(Err(DecodeError::TrailingEscape), decoder.decode_limited(&[255], 0));
+    }
+
+    #[test]
+    pub fn test_encoded_len() {
+        let strings = crate::read_string_lines("assets/test_data/l_comment").unwrap();
+        let strings = &strings[..1000];
+        let tables = [
+            crate::build_table_by_sampling(strings),
+            SymbolTableBuilder::new().wide_codes(true).train_from_samples(strings),
+            SymbolTableBuilder::identity_table(),
+        ];
+        for symbol_table in &tables {
+            let encoder = Encoder::from_table(symbol_table);
+            for str in strings.iter().chain([&String::new(), &"\u{ff}\u{7ff}".to_string()]) {
+                assert_eq!(encoder.encode_str(str).len(), encoder.encoded_len(str.as_bytes()));
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_usage_counts() {
+        let mut symbol_table = PerfectHashSymbolTable::new();
+        symbol_table.add_bytes(b"or");
+        symbol_table.add_bytes(b"lineitem");
+        symbol_table.finalize();
+        let encoder = Encoder::from_table_ref(&symbol_table);
+        let counts = encoder.usage_counts(&[b"orders", b"lineitem or", b""]);
+        assert_eq!(256, counts.len());
+        // "or" has code 0 and "lineitem" code 1 as the table is ordered by symbol length
+        assert_eq!(2, counts[0]);
+        assert_eq!(1, counts[1]);
+        // "d", "e", "r", "s" and " " are escaped
+        assert_eq!(5, counts[CODE_ESCAPE as usize]);
+        assert_eq!(8, counts.iter().sum::<u64>());
+    }
+
+    #[test]
+    pub fn test_clone_per_thread() {
+        let strings = crate::read_string_lines("assets/test_data/c_name").unwrap();
+        let strings = &strings[..400];
+        let symbol_table = crate::build_table_by_sampling(strings);
+        let encoder = Encoder::from_table(&symbol_table);
+        let decoder = Decoder::from_table(&symbol_table);
+        std::thread::scope(|scope| {
+            for chunk in strings.chunks(100) {
+                let (encoder, decoder) = (encoder, decoder.clone());
+                scope.spawn(move || {
+                    for str in chunk {
+                        assert_eq!(*str, decoder.decode(&encoder.encode_str(str)));
+                    }
+                });
+            }
+        });
+    }
+
+    #[test]
+    pub fn test_encode_bytes() {
+        let test_str = "tumcwitumvldb";
+        let symbol_table = SymbolTableBuilder::build_from(test_str);
+        let encoder = Encoder::from_table(&symbol_table);
+        assert_eq!(encoder.encode_str(test_str), encoder.encode_bytes(test_str.as_bytes()));
+
+        let invalid_utf8 = [0xc3u8, 0x28, 0xa0, 0xa1, 0xff];
+        let encoding = encoder.encode_bytes(&invalid_utf8);
+        assert_eq!(invalid_utf8.len() * 2, encoding.len());
+        assert_eq!(invalid_utf8.to_vec(), Decoder::from_table(&symbol_table).decode_bytes(&encoding));
+    }
+
+    #[test]
+    pub fn test_encode_into() {
+        let test_str = "tumcwitumvldb";
+        let symbol_table = SymbolTableBuilder::build_from(test_str);
+        let encoder = Encoder::from_table(&symbol_table);
+        let mut buf = vec![1u8; 64];
+        let len = encoder.encode_into(test_str.as_bytes(), &mut buf);
+        assert_eq!(len, buf.len());
+        assert_eq!(encoder.encode_str(test_str), buf);
+        let len = encoder.encode_into(b"tumc", &mut buf);
+        assert_eq!(1, len);
+        assert_eq!(encoder.encode_str("tumc"), buf);
+    }
+
+    #[test]
+    pub fn test_encode_bytes_fast() {
+        let strings = crate::read_string_lines("assets/test_data/l_comment").unwrap();
+        let strings = &strings[..1000];
+        let mut escaped_table = PerfectHashSymbolTable::new();
+        escaped_table.add_bytes(b"a\0");
+        escaped_table.add_bytes(b"lineitem");
+        escaped_table.add_bytes(b"tem\0");
+        escaped_table.set_escape_code(0);
+        escaped_table.finalize();
+        let tables = [
+            crate::build_table_by_sampling(strings),
+            SymbolTableBuilder::new().wide_codes(true).train_from_samples(strings),
+            SymbolTableBuilder::identity_table(),
+            Box::new(escaped_table) as Box<dyn SymbolTable>,
+        ];
+        let invalid_utf8 = [0xc3u8, 0x28, b'a', 0, b'l', b'i', b'n', b'e', b'i', b't', b'e', b'm', 0xff, b'a'];
+        for symbol_table in &tables {
+            let encoder = Encoder::from_table(symbol_table);
+            for input in strings.iter().map(|s| s.as_bytes()).chain([&b""[..], b"a", b"item", &invalid_utf8]) {
+                assert_eq!(encoder.encode_bytes(input), encoder.encode_bytes_fast(input));
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_decoder_fmt() {
+        let mut symbol_table = PerfectHashSymbolTable::new();
+        symbol_table.add_bytes(b"or");
+        symbol_table.add_bytes(b"\n\xff");
+        symbol_table.set_escape_code(0);
+        symbol_table.finalize();
+        let (_, decoder) = Decoder::from_table_bytes(&symbol_table.dump()).unwrap();
+        assert_eq!(symbol_table.to_string(), decoder.to_string());
+        // the codes skip the escape code 0
+        assert_eq!(
+            "Decoder { wide: false, identity: false, escape: 0, symbols: {1: b\"or\", 2: b\"\\n\\xff\"} }",
+            format!("{:?}", decoder)
+        );
+
+        let test_str = "tumcwitumvldb";
+        let wide_table = SymbolTableBuilder::new().wide_codes(true).train_from(test_str);
+        let decoder = Decoder::from_table(&wide_table);
+        assert_eq!(wide_table.to_string(), decoder.to_string());
+        let identity_decoder = Decoder::from_table(&SymbolTableBuilder::identity_table());
+        assert_eq!("[]", identity_decoder.to_string());
+        assert_eq!("Decoder { wide: false, identity: true, escape: 255, symbols: {} }", format!("{:?}", identity_decoder));
+    }
+
+    #[test]
+    pub fn test_encode_with_escape_count() {
+        let mut symbol_table = PerfectHashSymbolTable::new();
+        symbol_table.add_bytes(b"or");
+        symbol_table.add_bytes(b"lineitem");
+        symbol_table.finalize();
+        let encoder = Encoder::from_table_ref(&symbol_table);
+        for input in [&b"orders"[..], b"lineitem or", b"", b"\xff\xff"] {
+            let (encoding, escape_count) = encoder.encode_with_escape_count(input);
+            assert_eq!(encoder.encode_bytes(input), encoding);
+            assert_eq!(encoder.usage_counts(&[input])[CODE_ESCAPE as usize] as usize, escape_count);
+        }
+        assert_eq!(2, encoder.encode_with_escape_count(b"\xff\xff").1);
+
+        let wide_table = SymbolTableBuilder::new().wide_codes(true).train_from("tumcwitumvldb");
+        let (encoding, escape_count) = Encoder::from_table(&wide_table).encode_with_escape_count(b"tumcxyz");
+        assert_eq!(Encoder::from_table(&wide_table).encode_bytes(b"tumcxyz"), encoding);
+        assert_eq!(0, escape_count);
+    }
+
+    #[test]
+    pub fn test_max_expansion() {
+        let strings = crate::read_string_lines("assets/test_data/l_comment").unwrap();
+        let training = strings[..200].concat();
+        let tables = [
+            SymbolTableBuilder::new().train_from(&training),
+            SymbolTableBuilder::new().escape_code(0).train_from(&training),
+            SymbolTableBuilder::new().wide_codes(true).train_from(&training),
+            SymbolTableBuilder::identity_table(),
+        ];
+        let distinct_high = (128..=255u8).collect::<Vec<u8>>();
+        let all_bytes = (0..=255u8).rev().collect::<Vec<u8>>();
+        for symbol_table in &tables {
+            let encoder = Encoder::from_table(symbol_table);
+            let escape_run = vec![symbol_table.escape_code(); 1000];
+            for input in [&distinct_high[..], &all_bytes, &escape_run, training.as_bytes(), b"\xff", b""] {
+                let bound = Encoder::max_encoded_len(input.len());
+                assert!(bound <= Encoder::MAX_EXPANSION * input.len());
+                assert!(encoder.encode_bytes(input).len() <= bound);
+                assert!(encoder.encode_bytes_fast(input).len() <= bound);
+                assert!(encoder.encode_stored(input).len() <= bound);
+                let mut buf = Vec::with_capacity(bound);
+                encoder.encode_into(input, &mut buf);
+                assert!(buf.len() <= bound && buf.capacity() == bound);
+                assert!(encoder.encode_with_header(input, true).len() <= encoder.max_encoded_len_with_table(input.len()));
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_encode_stored() {
+        let test_str = "tumcwitumvldb";
+        let tables = [
+            SymbolTableBuilder::build_from(test_str),
+            SymbolTableBuilder::new().escape_code(0).train_from(test_str),
+            SymbolTableBuilder::new().wide_codes(true).train_from(test_str),
+            SymbolTableBuilder::identity_table(),
+        ];
+        for symbol_table in &tables {
+            let encoder = Encoder::from_table(symbol_table);
+            let decoder = Decoder::from_table(symbol_table);
+            for input in [&b"tumcwitumvldb"[..], b"", b"\x00\xff\xfe", "über".as_bytes()] {
+                let stored = encoder.encode_stored(input);
+                assert!(stored.len() <= Encoder::max_encoded_len(input.len()));
+                assert_eq!(input, decoder.decode_checked(&stored).unwrap());
+                assert_eq!(decoder.decode_checked(&encoder.encode_bytes(input)).unwrap(), input);
+            }
+        }
+        let symbol_table = &tables[0];
+        assert_eq!(vec![255, b'a', 255, 255], Encoder::from_table(symbol_table).encode_stored(b"a\xff"));
+    }
+
+    #[test]
+    pub fn test_decode_append() {
+        let test_str = "tumcwitumvldb";
+        let symbol_table = SymbolTableBuilder::build_from(test_str);
+        let encoder = Encoder::from_table(&symbol_table);
+        let decoder = Decoder::from_table(&symbol_table);
+        let mut out = String::from("row: ");
+        assert_eq!(test_str.len(), decoder.decode_append(&encoder.encode_str(test_str), &mut out));
+        assert_eq!(format!("row: {}", test_str), out);
+        out.clear();
+        let capacity = out.capacity();
+        assert_eq!(4, decoder.decode_append(&encoder.encode_str("tumc"), &mut out));
+        assert_eq!("tumc", out);
+        assert_eq!(capacity, out.capacity());
+    }
+
+    #[test]
+    pub fn test_encode_with_header() {
+        let test_str = "tumcwitumvldb";
+        let tables = [
+            SymbolTableBuilder::build_from(test_str),
+            SymbolTableBuilder::new().wide_codes(true).train_from(test_str),
+            SymbolTableBuilder::identity_table(),
+        ];
+        let invalid_utf8 = [0xc3u8, 0x28, b't', b'u', b'm', b'c', 0xff];
+        for symbol_table in &tables {
+            let encoder = Encoder::from_table(symbol_table);
+            assert_eq!(encoder.encode(test_str, true), encoder.encode_with_header(test_str.as_bytes(), true));
+            assert_eq!(encoder.encode_bytes(&invalid_utf8), encoder.encode_with_header(&invalid_utf8, false));
+            let encoding = encoder.encode_with_header(&invalid_utf8, true);
+            assert!(encoding.capacity() >= symbol_table.dump_len() + invalid_utf8.len() * 2);
+            let (table_end_pos, decoder) = Decoder::from_table_bytes(&encoding).unwrap();
+            assert_eq!(symbol_table.dump(), &encoding[..table_end_pos]);
+            assert_eq!(invalid_utf8.to_vec(), decoder.decode_bytes(&encoding[table_end_pos..]));
+        }
+    }
+
+    #[test]
+    pub fn test_encode_with_table_allocates_once() {
+        let strings = crate::read_string_lines("assets/test_data/l_comment").unwrap();
+        let symbol_table = crate::build_table_by_sampling(&strings[..1000]);
+        let encoder = Encoder::from_table(&symbol_table);
+        for str in &strings[..100] {
+            let encoding = encoder.encode(str, true);
+            // a reallocation would have grown the buffer past the room reserved up front
+            assert_eq!(symbol_table.dump_len() + str.len() * 2, encoding.capacity());
+            assert!(encoding.len() < encoding.capacity());
+        }
+    }
+
+    #[test]
+    pub fn test_max_len() {
+        let strings = crate::read_string_lines("assets/test_data/l_comment").unwrap();
+        let symbol_table = crate::build_table_by_sampling(&strings[..1000]);
+        let encoder = Encoder::from_table(&symbol_table);
+        let decoder = Decoder::from_table(&symbol_table);
+        let (mut encode_buf, mut decode_buf) = (Vec::new(), Vec::new());
+        for str in &strings[..100] {
+            encode_buf.reserve_exact(Encoder::max_encoded_len(str.len()));
+            let capacity = encode_buf.capacity();
+            encoder.encode_into(str.as_bytes(), &mut encode_buf);
+            assert_eq!(capacity, encode_buf.capacity());
+            decode_buf.reserve_exact(Decoder::max_decoded_len(encode_buf.len()));
+            let capacity = decode_buf.capacity();
+            decoder.decode_into(&encode_buf, &mut decode_buf);
+            assert_eq!(capacity, decode_buf.capacity());
+            assert_eq!(str.as_bytes(), decode_buf.as_slice());
+        }
+        assert_eq!(16, Encoder::max_encoded_len(8));
+        assert_eq!(64, Decoder::max_decoded_len(8));
+        let escaped = encoder.encode_bytes(&[0xff; 8]);
+        assert_eq!(Encoder::max_encoded_len(8), escaped.len());
+    }
+
+    #[test]
+    pub fn test_decode_bytes() {
+        let test_str = "tumcwitumvldb";
+        let symbol_table = SymbolTableBuilder::build_from(test_str);
+        let encoder = Encoder::from_table(&symbol_table);
+        let decoder = Decoder::from_table(&symbol_table);
+        let invalid_utf8 = [0xc3u8, 0x28, b't', b'u', b'm', b'c', 0xa0, 0xa1, 0xff, b'b'];
+        let encoding = encoder.encode_bytes(&invalid_utf8);
+        assert_eq!(invalid_utf8.to_vec(), decoder.decode_bytes(&encoding));
+    }
+
+    #[test]
+    pub fn test_decode_into() {
+        let test_str = "tumcwitumvldb";
+        let symbol_table = SymbolTableBuilder::build_from(test_str);
+        let encoder = Encoder::from_table(&symbol_table);
+        let decoder = Decoder::from_table(&symbol_table);
+        let mut buf = Vec::new();
+        let len = decoder.decode_into(&encoder.encode_str(test_str), &mut buf);
+        assert_eq!(test_str.len(), len);
+        assert_eq!(test_str.as_bytes(), buf.as_slice());
+        let capacity = buf.capacity();
+        let len = decoder.decode_into(&encoder.encode_str("tumc"), &mut buf);
+        assert_eq!(4, len);
+        assert_eq!(b"tumc", buf.as_slice());
+        assert_eq!(capacity, buf.capacity());
+    }
+
+    #[test]
+    pub fn test_decode_all_into_arena() {
+        let strings = crate::read_string_lines("assets/test_data/c_name").unwrap();
+        let strings = &strings[..1000];
+        let (symbol_table, encodings) = crate::encode_all_strings(strings);
+        let decoder = Decoder::from_table(&symbol_table);
+        let (arena, ranges) = decoder.decode_all_into_arena(&encodings);
+        assert_eq!(strings.len(), ranges.len());
+        assert_eq!(strings.iter().map(|s| s.len()).sum::<usize>(), arena.len());
+        for (str, range) in strings.iter().zip(ranges) {
+            assert_eq!(str.as_bytes(), &arena[range]);
+        }
+
+        let wide_table = SymbolTableBuilder::new().wide_codes(true).train_from_samples(strings);
+        let encoder = Encoder::from_table(&wide_table);
+        let encodings = strings.iter().map(|s| encoder.encode_str(s)).collect::<Vec<Vec<u8>>>();
+        let (arena, ranges) = Decoder::from_table(&wide_table).decode_all_into_arena(&encodings);
+        for (str, range) in strings.iter().zip(ranges) {
+            assert_eq!(str.as_bytes(), &arena[range]);
+        }
+    }
+
+    /// decode(encode(x)) == x for the given input with tables trained on it, with both code widths
+    fn assert_round_trip(input: &[u8]) {
+        for wide in [false, true] {
+            let symbol_table = SymbolTableBuilder::new().wide_codes(wide).train_from_bytes(input);
+            let encoding = Encoder::from_table(&symbol_table).encode_bytes(input);
+            assert!(encoding.len() <= input.len() * 2);
+            let decoder = Decoder::from_table(&symbol_table);
+            assert_eq!(input, decoder.decode_bytes(&encoding).as_slice());
+            assert_eq!(input, decoder.decode_checked(&encoding).unwrap().as_slice());
+            let (_, loaded_decoder) = Decoder::from_table_bytes(&symbol_table.dump()).unwrap();
+            assert_eq!(input, loaded_decoder.decode_bytes(&encoding).as_slice());
+        }
+    }
+
+    #[test]
+    pub fn test_round_trip_arbitrary_bytes() {
+        assert_round_trip(b"");
+        assert_round_trip(&[0xff; 100]);
+        assert_round_trip(&[0; 100]);
+        assert_round_trip(&[b'a'; 17]);
+        assert_round_trip(&[0xff, 0, 0xff, 0, 0xff]);
+        let mut rand = 42u64;
+        for len in [1, 2, 3, 7, 8, 9, 100, 1000, 5000] {
+            for alphabet in [2, 16, 256] {
+                let input = (0..len)
+                    .map(|_| {
+                        rand = crate::core::xorshift(rand);
+                        (rand % alphabet) as u8
+                    })
+                    .collect::<Vec<u8>>();
+                assert_round_trip(&input);
+                // repeated runs of a few random bytes, so the table learns long symbols
+                let runs = input.iter().flat_map(|&b| [b; 11]).take(len).collect::<Vec<u8>>();
+                assert_round_trip(&runs);
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_decode_concat() {
+        let strings = crate::read_string_lines("assets/test_data/c_name").unwrap();
+        let strings = &strings[..100];
+        let (symbol_table, encodings) = crate::encode_all_strings(strings);
+        let decoder = Decoder::from_table(&symbol_table);
+        assert_eq!(strings.join("\n"), decoder.decode_concat(&encodings, "\n"));
+        assert_eq!(strings.concat(), decoder.decode_concat(&encodings, ""));
+        assert_eq!("", decoder.decode_concat(&[], ", "));
+    }
+
+    #[test]
+    pub fn test_decode_iter() {
+        let strings = crate::read_string_lines("assets/test_data/c_name").unwrap();
+        let strings = &strings[..100];
+        let (symbol_table, encodings) = crate::encode_all_strings(strings);
+        let decoder = Decoder::from_table(&symbol_table);
+        assert_eq!(strings, decoder.decode_iter(&encodings).collect::<Vec<String>>());
+        assert_eq!(&strings[..3], decoder.decode_iter(&encodings).take(3).collect::<Vec<String>>());
+        let pos = decoder.decode_iter(&encodings).position(|s| s == strings[42]).unwrap();
+        assert_eq!(strings.iter().position(|s| *s == strings[42]).unwrap(), pos);
+        assert_eq!(None, decoder.decode_iter(&[]).next());
+    }
+
+    #[test]
+    pub fn test_decode_trailing_escape() {
+        let test_str = "tumcwitumvldb";
+        let symbol_table = SymbolTableBuilder::build_from(test_str);
+        let encoder = Encoder::from_table(&symbol_table);
+        let decoder = Decoder::from_table(&symbol_table);
+        let mut encoding = encoder.encode_str(test_str);
+        encoding.push(255);
+        assert_eq!(test_str, decoder.decode(&encoding));
+        assert_eq!(Vec::<u8>::new(), decoder.decode_bytes(&[255]));
+    }
+
+    #[test]
+    pub fn test_encode_framed() {
+        let test_str = "tumcwitumvldb";
+        let tables = [
+            SymbolTableBuilder::build_from(test_str),
+            SymbolTableBuilder::new().wide_codes(true).train_from(test_str),
+            SymbolTableBuilder::identity_table(),
+        ];
+        for symbol_table in &tables {
+            let encoder = Encoder::from_table(symbol_table);
+            for input in [test_str.as_bytes(), b"", b"tumc\xff\x00"] {
+                let framed = encoder.encode_framed(input);
+                assert_eq!(symbol_table.dump_len() as u8, framed[0]);
+                assert_eq!(encoder.encode_with_header(input, true), framed[1..]);
+                assert_eq!(input, Decoder::decode_framed(&framed).unwrap());
+            }
+        }
+
+        // table bytes the parser does not read are skipped
+        let symbol_table = &tables[0];
+        let dump = symbol_table.dump();
+        let encoding = Encoder::from_table(symbol_table).encode_str(test_str);
+        let framed = [&[dump.len() as u8 + 2][..], &dump, b"??", &encoding].concat();
+        assert_eq!(test_str.as_bytes(), Decoder::decode_framed(&framed).unwrap());
+
+        let framed = Encoder::from_table(symbol_table).encode_framed(test_str.as_bytes());
+        assert_eq!(DecodeError::TooShort, Decoder::decode_framed(&framed[..framed[0] as usize]).unwrap_err());
+        assert_eq!(DecodeError::TooShort, Decoder::decode_framed(&[]).unwrap_err());
+        let framed = [&[dump.len() as u8 - 1][..], &dump, &encoding].concat();
+        assert!(Decoder::decode_framed(&framed).is_err());
+    }
+
+    #[test]
+    pub fn test_decoded_prefix() {
+        let strings = crate::read_string_lines("assets/test_data/c_name").unwrap();
+        let strings = &strings[..100];
+        let mut input = strings.concat().into_bytes();
+        input.extend_from_slice(b"\xff\x00");
+        let tables = [
+            crate::build_table_by_sampling(strings),
+            SymbolTableBuilder::new().wide_codes(true).train_from_samples(strings),
+            SymbolTableBuilder::identity_table(),
+        ];
+        for symbol_table in &tables {
+            let encoder = Encoder::from_table(symbol_table);
+            let decoder = Decoder::from_table(symbol_table);
+            for str in strings.iter().map(|s| s.as_bytes()).chain([&input[..], b""]) {
+                let encoding = encoder.encode_bytes(str);
+                for n in [0, 1, 3, 8, 9, str.len(), str.len() + 5] {
+                    assert_eq!(&str[..n.min(str.len())], decoder.decoded_prefix(&encoding, n).as_slice());
+                }
+            }
+        }
+        let encoding = Encoder::from_table(&tables[0]).encode_str("Customer#000000001");
+        let decoder = Decoder::from_table(&tables[0]);
+        assert_eq!(b"Customer#".to_vec(), decoder.decoded_prefix(&encoding, 9));
+        assert_eq!(Vec::<u8>::new(), decoder.decoded_prefix(&[255], 4));
+    }
+
+    #[test]
+    pub fn test_decode_one() {
+        let strings = crate::read_string_lines("assets/test_data/c_name").unwrap();
+        let mut strings = strings[..100].to_vec();
+        strings.insert(10, String::new());
+        let tables = [
+            crate::build_table_by_sampling(&strings),
+            SymbolTableBuilder::new().wide_codes(true).train_from_samples(&strings),
+            SymbolTableBuilder::identity_table(),
+        ];
+        for symbol_table in &tables {
+            let encoder = Encoder::from_table(symbol_table);
+            let decoder = Decoder::from_table(symbol_table);
+            let buf = strings.iter().flat_map(|s| encoder.encode_str(s)).collect::<Vec<u8>>();
+            let mut pos = 0;
+            for str in &strings {
+                let (decoded, consumed) = decoder.decode_one(&buf[pos..], str.len()).unwrap();
+                assert_eq!(str, &decoded);
+                pos += consumed;
+            }
+            assert_eq!(buf.len(), pos);
+            assert_eq!(Err(DecodeError::TooShort), decoder.decode_one(&buf, buf.len() * 8 + 1));
+        }
+
+        let strings = vec!["héllo wörld ééé"; 2000];
+        let (symbol_table, encodings) = crate::encode_all_strings(&strings);
+        let decoder = Decoder::from_table(&symbol_table);
+        let (decoded, consumed) = decoder.decode_one(&encodings[0], strings[0].len()).unwrap();
+        assert_eq!((strings[0], encodings[0].len()), (decoded.as_str(), consumed));
+        // a length ending inside a symbol or inside a character is not the length of this record
+        let mut cuts = 0;
+        for n in 1..strings[0].len() {
+            match decoder.decode_one(&encodings[0], n) {
+                Ok((decoded, _)) => assert_eq!(&strings[0][..n], decoded),
+                Err(err) => {
+                    assert_eq!(DecodeError::InvalidRecordLength, err);
+                    cuts += 1;
+                }
+            }
+        }
+        assert!(cuts > 0);
+        assert_eq!(Err(DecodeError::InvalidRecordLength), decoder.decode_one(&encodings[0], 2));
+        assert!(!strings[0].is_char_boundary(2));
+    }
+
+    #[test]
+    pub fn test_record_marker() {
+        let strings = crate::read_string_lines("assets/test_data/l_comment").unwrap();
+        let samples = strings[..2000].iter().map(|s| s.as_str()).collect::<Vec<&str>>();
+        let mut full_table = PerfectHashSymbolTable::new();
+        for byte in 0..CODE_ESCAPE {
+            assert!(full_table.add_bytes(&[byte, b'#']));
+        }
+        full_table.finalize();
+        let full_table: Box<dyn SymbolTable> = Box::new(full_table);
+        assert_eq!(Err(EncodeError::NoMarkerCode), Encoder::from_table(&full_table).encode_with_marker(b"abc"));
+        let identity_table = SymbolTableBuilder::identity_table();
+        assert_eq!(Err(EncodeError::NoMarkerCode), Encoder::from_table(&identity_table).encode_with_marker(b"abc"));
+
+        for (wide, escape) in [(false, CODE_ESCAPE), (false, 0), (true, CODE_ESCAPE)] {
+            let mut builder = SymbolTableBuilder::new().wide_codes(wide).escape_code(escape).record_marker(true);
+            let symbol_table = builder.train_from_samples(&samples);
+            assert!(symbol_table.len() < if wide { 3840 } else { CODE_ESCAPE as usize });
+            let encoder = Encoder::from_table(&symbol_table);
+            let decoder = Decoder::from_table(&symbol_table);
+            let marker = encoder.encode_with_marker(b"").unwrap();
+            // records holding the escape and the bytes of the marker as literals
+            let mut records = strings[..50].iter().map(|s| s.as_bytes().to_vec()).collect::<Vec<Vec<u8>>>();
+            records.push(Vec::new());
+            records.push([&marker[..], &[escape, b'x'], &marker].concat());
+            let mut stream = Vec::new();
+            let mut record_starts = Vec::new();
+            for record in &records {
+                record_starts.push(stream.len());
+                stream.extend(encoder.encode_with_marker(record).unwrap());
+            }
+            assert_eq!(records, decoder.decode_records(&stream));
+            // a damaged record leaves the records after it intact
+            stream[record_starts[3]] = encoder.encode_bytes(b"a")[0];
+            let decoded = decoder.decode_records(&stream);
+            assert_eq!(records.len(), decoded.len());
+            assert_ne!(records[3], decoded[3]);
+            assert_eq!(records[4..], decoded[4..]);
+            // an unfinished record is left out
+            assert_eq!(records[..1], decoder.decode_records(&stream[..record_starts[1] + 3]));
+        }
+    }
+
+    #[test]
+    pub fn test_decode_utf8() {
+        let test_str = "tumcwitumvldb über";
+        let symbol_table = SymbolTableBuilder::build_from(test_str);
+        let encoder = Encoder::from_table(&symbol_table);
+        let decoder = Decoder::from_table(&symbol_table);
+        assert_eq!(test_str, decoder.decode_utf8(&encoder.encode_str(test_str)).unwrap());
+        assert_eq!("", decoder.decode_utf8(&[]).unwrap());
+        let invalid = decoder.decode_utf8(&encoder.encode_bytes(b"tumc\xc3\x28")).unwrap_err();
+        assert_eq!(b"tumc\xc3\x28".to_vec(), invalid.into_bytes());
+    }
+
+    #[test]
+    pub fn test_decode_with_tab() {
+        let test_str = "tumcwitumvldb über straße";
+        for escape in [0, CODE_ESCAPE] {
+            let symbol_table = SymbolTableBuilder::new().escape_code(escape).train_from("tumcwitumvldb");
+            let encoding = Encoder::from_table(&symbol_table).encode_str(test_str);
+            // the escaped bytes of the non-ASCII characters are not widened to characters each
+            assert_eq!(test_str, Decoder::decode_with_tab(&symbol_table, &encoding));
+            // a truncated buffer ends with a lone escape code
+            let encoding = [&encoding[..], &[escape]].concat();
+            assert_eq!(test_str, Decoder::decode_with_tab(&symbol_table, &encoding));
+            assert_eq!("", Decoder::decode_with_tab(&symbol_table, &[escape]));
+            let encoding = Encoder::from_table(&symbol_table).encode_bytes(b"tum\xff");
+            assert_eq!("tum\u{fffd}", Decoder::decode_with_tab(&symbol_table, &encoding));
+        }
+    }
+
+    #[test]
+    pub fn test_decode_short_encodings() {
+        let input = b"tumc\x00wi\xfftum\x00\x00vldb\xff\xffzq";
+        for escape in [0, CODE_ESCAPE] {
+            let symbol_table = SymbolTableBuilder::new().escape_code(escape).train_from("tumcwitumvldb");
+            let encoder = Encoder::from_table(&symbol_table);
+            let decoder = Decoder::from_table(&symbol_table);
+            // every encoding of 1 to 5 bytes only goes through the block of the last bytes
+            for start in 0..input.len() {
+                for end in start + 1..=(start + 5).min(input.len()) {
+                    let encoding = encoder.encode_bytes(&input[start..end]);
+                    assert_eq!(&input[start..end], decoder.decode_bytes(&encoding).as_slice());
+                }
+            }
+            let code = encoder.encode_bytes(b"t")[0];
+            assert_eq!(b"t".to_vec(), decoder.decode_bytes(&[code, escape]));
+            assert_eq!(b"tt".to_vec(), decoder.decode_bytes(&[code, code, escape]));
+            assert_eq!(b"zt".to_vec(), decoder.decode_bytes(&[escape, b'z', code, escape]));
+        }
+    }
+
+    #[test]
+    pub fn test_decode_full_length_symbols() {
+        // every code decodes to 8 bytes, so the last store ends exactly at the reserved length
+        let input = "abcdefgh".repeat(64);
+        for wide in [false, true] {
+            let symbol_table = SymbolTableBuilder::new().wide_codes(wide).train_from(&input);
+            let encoding = Encoder::from_table(&symbol_table).encode_bytes(input.as_bytes());
+            let decoder = Decoder::from_table(&symbol_table);
+            for end in 1..=encoding.len() {
+                let mut decode_buf = Vec::with_capacity(Decoder::max_decoded_len(end));
+                let decoded_len = decoder.decode_into(&encoding[..end], &mut decode_buf);
+                assert!(decoded_len <= Decoder::max_decoded_len(end));
+                assert!(input.as_bytes().starts_with(&decode_buf));
+            }
+            let mut decode_buf = Vec::new();
+            decoder.decode_into(&encoding, &mut decode_buf);
+            assert_eq!(input.as_bytes(), decode_buf.as_slice());
+        }
+    }
+
+    #[test]
+    pub fn test_decode_single_symbol() {
+        let input = "tumcwitumvldb".repeat(32);
+        for wide in [false, true] {
+            let symbol_table = SymbolTableBuilder::new().wide_codes(wide).train_from(&input);
+            let encoder = Encoder::from_table(&symbol_table);
+            let decoder = Decoder::from_table(&symbol_table);
+            let mut long_symbols = 0;
+            for (_, symbol) in symbol_table.iter_symbols().filter(|(_, symbol)| symbol.len() > 1) {
+                // a whole symbol is its own longest match, so it is encoded as one code
+                let encoding = encoder.encode_bytes(&symbol);
+                assert_eq!(Some(symbol.clone()), decoder.single_symbol(&encoding));
+                assert_eq!(symbol, decoder.decode_bytes(&encoding));
+                long_symbols += 1;
+            }
+            assert!(long_symbols > 0);
+            let encoding = encoder.encode_bytes(input.as_bytes());
+            assert!(decoder.single_symbol(&encoding).is_none());
+            assert_eq!(input.as_bytes(), decoder.decode_bytes(&encoding).as_slice());
+        }
+        let symbol_table = SymbolTableBuilder::new().train_from(&input);
+        let decoder = Decoder::from_table(&symbol_table);
+        assert!(decoder.single_symbol(&[CODE_ESCAPE]).is_none());
+        assert!(decoder.single_symbol(&[CODE_ESCAPE, b'z']).is_none());
+        assert_eq!(b"".to_vec(), decoder.decode_bytes(&[CODE_ESCAPE]));
+    }
+
+    #[test]
+    pub fn test_escape_code() {
+        let strings = crate::read_string_lines("assets/test_data/l_comment").unwrap();
+        let mut input = strings[..500].concat().into_bytes();
+        input.extend((0..=255u8).cycle().take(1000));
+        for escape in [0, b' ', 0xfe, CODE_ESCAPE] {
+            let symbol_table = SymbolTableBuilder::new().escape_code(escape).train_from_bytes(&input);
+            assert_eq!(escape, symbol_table.escape_code());
+            let encoding = Encoder::from_table(&symbol_table).encode_bytes(&input);
+            let decoder = Decoder::from_table(&symbol_table);
+            assert_eq!(escape, decoder.escape_code());
+            assert_eq!(input, decoder.decode_bytes(&encoding));
+            assert_eq!(input, decoder.decode_checked(&encoding).unwrap());
+
+            let buf = symbol_table.dump();
+            assert_eq!(symbol_table.dump_len(), buf.len());
+            let (table_end_pos, loaded_decoder) = Decoder::from_table_bytes(&buf).unwrap();
+            assert_eq!(buf.len(), table_end_pos);
+            assert_eq!(escape, loaded_decoder.escape_code());
+            assert_eq!(input, loaded_decoder.decode_bytes(&encoding));
+            let loaded_table = SymbolTableBuilder::load_from_bytes(&buf).unwrap();
+            assert_eq!(encoding, Encoder::from_table(&loaded_table).encode_bytes(&input));
+        }
+        // the default escape code keeps the dump format unchanged
+        let default_table = SymbolTableBuilder::build_from_bytes(&input);
+        let custom_table = SymbolTableBuilder::new().escape_code(0).train_from_bytes(&input);
+        assert_eq!(default_table.dump_len() + 1, custom_table.dump_len());
+    }
+
+    #[test]
+    pub fn test_decode_self_describing() {
+        let columns = ["tumcwitumvldb", "", "paqvawflxucgajxfzxwooypirnzkahobfvxzhrerdwzkerwwolqfbafwslwhsvuitbtgk"];
+        let mut buf = Vec::new();
+        for str in columns {
+            let symbol_table = SymbolTableBuilder::build_from(str);
+            buf.extend_from_slice(&Encoder::from_table(&symbol_table).encode_self_describing(str));
+        }
+        let identity_table = SymbolTableBuilder::identity_table();
+        buf.extend_from_slice(&Encoder::from_table(&identity_table).encode_self_describing("stored"));
+
+        let mut decoded = Vec::new();
+        let mut pos = 0;
+        while pos < buf.len() {
+            let (block_len, str) = Decoder::decode_self_describing(&buf[pos..]).unwrap();
+            decoded.push(str);
+            pos += block_len;
+        }
+        assert_eq!(buf.len(), pos);
+        assert_eq!(vec![columns[0], columns[1], columns[2], "stored"], decoded);
+        let (first_len, _) = Decoder::decode_self_describing(&buf).unwrap();
+        assert_eq!(Err(DecodeError::TooShort), Decoder::decode_self_describing(&buf[..first_len - 1]));
+    }
+}
@@ -1,23 +1,48 @@
-use std::cmp::min;
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::min;
 
-use crate::core::{bulk_load, CODE_MAX, U64_SIZE};
+use crate::core::{bulk_load, U64_SIZE};
 
+/// the counters are allocated on the heap, the dense concat counters alone take hundreds of KB which
+/// would overflow the stack of small worker threads
 pub(crate) struct Counter {
-    single_low: [u8; Counter::ENTRY_SIZE],
-    single_high: [u8; Counter::ENTRY_SIZE],
-    concat_low: [[u8; Counter::ENTRY_SIZE]; Counter::ENTRY_SIZE],
-    concat_high: [[u8; Counter::ENTRY_SIZE >> 1]; Counter::ENTRY_SIZE],
+    entry_size: usize,
+    single_low: Vec<u8>,
+    single_high: Vec<u8>,
+    // entry_size * entry_size counters, row-major by the first code
+    concat_low: Vec<u8>,
+    // entry_size * entry_size 4-bits counters, two counters packed in a byte
+    concat_high: Vec<u8>,
+    // counters of the pairs seen so far, used instead of the dense concat counters if present
+    sparse_concat: Option<BTreeMap<(usize, usize), u32>>,
 }
 
 impl Counter {
-    pub(crate) const ENTRY_SIZE: usize = CODE_MAX as usize;
+    /// create counters for the codes in `0..entry_size`
+    pub fn new(entry_size: usize) -> Counter {
+        Counter {
+            entry_size,
+            single_low: vec![0u8; entry_size],
+            single_high: vec![0u8; entry_size],
+            concat_low: vec![0u8; entry_size * entry_size],
+            concat_high: vec![0u8; entry_size * (entry_size >> 1)],
+            sparse_concat: None,
+        }
+    }
 
-    pub fn new() -> Counter {
+    /// create counters that count the pairs in a sparse map instead of the dense concat counters,
+    /// which take `entry_size * entry_size * 1.5` bytes, so the memory only grows with the number of
+    /// distinct pairs that are counted
+    pub fn new_sparse(entry_size: usize) -> Counter {
         Counter {
-            single_low: [0u8; Counter::ENTRY_SIZE],
-            single_high: [0u8; Counter::ENTRY_SIZE],
-            concat_low: [[0u8; Counter::ENTRY_SIZE]; Counter::ENTRY_SIZE],
-            concat_high: [[0u8; Counter::ENTRY_SIZE >> 1]; Counter::ENTRY_SIZE],
+            entry_size,
+            single_low: vec![0u8; entry_size],
+            single_high: vec![0u8; entry_size],
+            concat_low: Vec::new(),
+            concat_high: Vec::new(),
+            sparse_concat: Some(BTreeMap::new()),
         }
     }
 
@@ -32,22 +57,27 @@ impl Counter {
     }
 
     pub fn inc_concat(&mut self, pos1: usize, pos2: usize) {
-        if self.concat_low[pos1][pos2] == 0 {
+        if let Some(sparse_concat) = &mut self.sparse_concat {
+            *sparse_concat.entry((pos1, pos2)).or_insert(0) += 1;
+            return;
+        }
+        let pos_low = pos1 * self.entry_size + pos2;
+        if self.concat_low[pos_low] == 0 {
             // increment high early (when low==0, not when low==255). This means (high > 0) <=> (cnt > 0)
             // inc 4-bits high counter with 1<<0 (1) or 1<<4 (16) -- depending on whether pos2 is even or odd, respectively
             // we take our chances with overflow (4K max val, on a 8K sample)
-            let pos_high = pos2 >> 1;
-            self.concat_high[pos1][pos_high] = self.concat_high[pos1][pos_high]
+            let pos_high = pos1 * (self.entry_size >> 1) + (pos2 >> 1);
+            self.concat_high[pos_high] = self.concat_high[pos_high]
                 .checked_add(1 << ((pos2 & 1) << 2))
-                .unwrap_or(self.concat_high[pos1][pos_high]);
+                .unwrap_or(self.concat_high[pos_high]);
         }
-        self.concat_low[pos1][pos2] = self.concat_low[pos1][pos2].wrapping_add(1);
+        self.concat_low[pos_low] = self.concat_low[pos_low].wrapping_add(1);
     }
 
     /// read 16-bits single symbol counter, split into two 8-bits numbers (count1Low, count1High), while skipping over zeros.
     /// it will advance pos1 to the next nonzero counter in register range
     pub fn get_single_and_forward(&mut self, pos: &mut usize) -> u32 {
-        let mut high = bulk_load(&self.single_high[*pos..min(*pos + U64_SIZE, Self::ENTRY_SIZE)]);
+        let mut high = bulk_load(&self.single_high[*pos..min(*pos + U64_SIZE, self.entry_size)]);
         let zero = if high > 0 {
             high.trailing_zeros() >> 3
         } else {
@@ -55,7 +85,7 @@ impl Counter {
         };
         high = (high >> (zero << 3)) & 0xff; // advance to nonzero counter
         *pos += zero as usize;
-        if (*pos >= Self::ENTRY_SIZE) || high == 0 {
+        if (*pos >= self.entry_size) || high == 0 {
             return 0; // all zero
         }
 
@@ -70,9 +100,22 @@ impl Counter {
     /// read 12-bits pairwise symbol counter, split into low 8-bits and high 4-bits number while skipping over zeros
     /// it will advance pos2 to the next nonzero counter in register range
     pub fn get_concat_and_forward(&mut self, pos1: usize, pos2: &mut usize) -> u32 {
+        if let Some(sparse_concat) = &self.sparse_concat {
+            return match sparse_concat.range((pos1, *pos2)..(pos1 + 1, 0)).next() {
+                Some((&(_, next_pos2), &cnt)) => {
+                    *pos2 = next_pos2;
+                    cnt
+                }
+                None => {
+                    *pos2 = self.entry_size;
+                    0
+                }
+            };
+        }
+        let row = pos1 * (self.entry_size >> 1);
         let start = *pos2 >> 1;
-        let end = min(start + U64_SIZE, Self::ENTRY_SIZE >> 1);
-        let mut high = bulk_load(&self.concat_high[pos1][start..end]);
+        let end = min(start + U64_SIZE, self.entry_size >> 1);
+        let mut high = bulk_load(&self.concat_high[row + start..row + end]);
         high >>= (*pos2 & 1) << 2; // odd pos2: ignore the lowest 4 bits & we see only 15 counters
         let zero = if high > 0 { // number of zero 4-bits counters
             high.trailing_zeros() >> 2
@@ -82,11 +125,11 @@ impl Counter {
 
         high = (high >> (zero << 2)) & 0xf; // advance to nonzero counter
         *pos2 += zero as usize;
-        if (*pos2 >= Self::ENTRY_SIZE) || high == 0 {
+        if (*pos2 >= self.entry_size) || high == 0 {
             return 0; // all zero
         }
 
-        let low = self.concat_low[pos1][*pos2] as u64;
+        let low = self.concat_low[pos1 * self.entry_size + *pos2] as u64;
         if low > 0 {
             // high is incremented early and low late, so decrement high (unless low==0)
             high -= 1;
@@ -94,43 +137,37 @@ impl Counter {
         ((high << 8) | low) as u32
     }
 
-    pub fn backup_single(&self) -> [u8; Self::ENTRY_SIZE * 2] {
-        let mut buf = [0u8; Self::ENTRY_SIZE * 2];
-        unsafe {
-            std::ptr::copy_nonoverlapping(self.single_low.as_ptr(), buf.as_mut_ptr(), Self::ENTRY_SIZE);
-            std::ptr::copy_nonoverlapping(self.single_high.as_ptr(), buf.as_mut_ptr().add(Self::ENTRY_SIZE), Self::ENTRY_SIZE);
-        }
+    pub fn backup_single(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(self.entry_size * 2);
+        buf.extend_from_slice(&self.single_low);
+        buf.extend_from_slice(&self.single_high);
         buf
     }
 
-    pub fn restore_single(&mut self, buf: [u8; Self::ENTRY_SIZE * 2]) {
-        unsafe {
-            std::ptr::copy_nonoverlapping(buf.as_ptr(), self.single_low.as_mut_ptr(), Self::ENTRY_SIZE);
-            std::ptr::copy_nonoverlapping(buf.as_ptr().add(Self::ENTRY_SIZE), self.single_high.as_mut_ptr(), Self::ENTRY_SIZE);
-        }
+    pub fn restore_single(&mut self, buf: &[u8]) {
+        self.single_low.copy_from_slice(&buf[..self.entry_size]);
+        self.single_high.copy_from_slice(&buf[self.entry_size..]);
     }
 
     pub fn reset(&mut self) {
-        let single_low_prt = self.single_low.as_mut_ptr();
-        let single_high_prt = self.single_high.as_mut_ptr();
-        let concat_low_prt = self.concat_low.as_mut_ptr();
-        let concat_high_prt = self.concat_high.as_mut_ptr();
-        unsafe {
-            single_low_prt.write_bytes(0, self.single_low.len());
-            single_high_prt.write_bytes(0, self.single_high.len());
-            concat_low_prt.write_bytes(0, self.concat_low.len());
-            concat_high_prt.write_bytes(0, self.concat_high.len());
+        self.single_low.fill(0);
+        self.single_high.fill(0);
+        self.concat_low.fill(0);
+        self.concat_high.fill(0);
+        if let Some(sparse_concat) = &mut self.sparse_concat {
+            sparse_concat.clear();
         }
     }
 }
 
 #[cfg(test)]
 mod test {
+    use crate::core::CODE_MAX;
     use crate::core::counter::Counter;
 
     #[test]
     pub fn test_counter() {
-        let mut counter = Counter::new();
+        let mut counter = Counter::new(CODE_MAX as usize);
         counter.inc_single(0);
         assert_eq!(1, counter.single_low[0]);
         counter.inc_single(5);
@@ -157,4 +194,28 @@ mod test {
         counter.inc_concat(0, 0);
         assert_eq!(256, counter.get_concat_and_forward(0, &mut pos));
     }
+
+    #[test]
+    pub fn test_sparse_counter() {
+        let mut counter = Counter::new_sparse(CODE_MAX as usize);
+        assert!(counter.concat_low.is_empty());
+        for _ in 0..300 {
+            counter.inc_concat(3, 7);
+        }
+        counter.inc_concat(3, 9);
+        counter.inc_concat(4, 1);
+        let mut pos2 = 0usize;
+        assert_eq!(300, counter.get_concat_and_forward(3, &mut pos2));
+        assert_eq!(7, pos2);
+        pos2 += 1;
+        assert_eq!(1, counter.get_concat_and_forward(3, &mut pos2));
+        assert_eq!(9, pos2);
+        pos2 += 1;
+        assert_eq!(0, counter.get_concat_and_forward(3, &mut pos2));
+        assert_eq!(CODE_MAX as usize, pos2);
+
+        counter.reset();
+        pos2 = 0;
+        assert_eq!(0, counter.get_concat_and_forward(4, &mut pos2));
+    }
 }
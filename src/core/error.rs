@@ -0,0 +1,76 @@
+use core::error::Error;
+use core::fmt::{Display, Formatter};
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    /// the buffer ends before the data it declares
+    TooShort,
+    /// the buffer does not start with the symbol table magic number
+    BadMagic,
+    /// the symbol table was dumped by an unsupported format version
+    UnsupportedVersion(u8),
+    /// the endian byte is neither little nor big endian
+    UnknownEndian(u8),
+    /// the symbol table header has flag bits this version does not know
+    UnknownFlags(u8),
+    /// the dumped symbols can not form a symbol table
+    InvalidSymbolTable,
+    /// the length histogram declares more symbols than the codes can address
+    TooManySymbols(usize),
+    /// the varint is longer than the longest encoding of a u64
+    InvalidVarint,
+    /// the encoding has a code that is not in the symbol table
+    InvalidCode(u16),
+    /// the encoding ends with an escape code that has no escaped byte after it
+    TrailingEscape,
+    /// the block names a checksum algorithm this version does not know
+    UnknownChecksum(u8),
+    /// the checksum stored in the block does not match its content
+    ChecksumMismatch,
+    /// the decoded output would be larger than the allowed size
+    OutputTooLarge,
+    /// the record length ends inside a symbol or a UTF-8 character of the decoded record
+    InvalidRecordLength,
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            DecodeError::TooShort => write!(f, "buffer is too short"),
+            DecodeError::BadMagic => write!(f, "bad symbol table magic number"),
+            DecodeError::UnsupportedVersion(v) => write!(f, "unsupported symbol table format version {}", v),
+            DecodeError::UnknownEndian(b) => write!(f, "unknown endian byte {}", b),
+            DecodeError::UnknownFlags(b) => write!(f, "unknown symbol table flags {:#04x}", b),
+            DecodeError::InvalidSymbolTable => write!(f, "invalid symbol table"),
+            DecodeError::TooManySymbols(n) => write!(f, "symbol table declares too many symbols ({})", n),
+            DecodeError::InvalidVarint => write!(f, "invalid varint"),
+            DecodeError::InvalidCode(c) => write!(f, "invalid code {}", c),
+            DecodeError::TrailingEscape => write!(f, "escape code without an escaped byte at the end"),
+            DecodeError::UnknownChecksum(b) => write!(f, "unknown checksum algorithm {}", b),
+            DecodeError::ChecksumMismatch => write!(f, "checksum mismatch"),
+            DecodeError::OutputTooLarge => write!(f, "decoded output is too large"),
+            DecodeError::InvalidRecordLength => write!(f, "record length ends inside a symbol or character"),
+        }
+    }
+}
+
+impl Error for DecodeError {}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum EncodeError {
+    /// the dumped symbol table would be larger than the allowed size, which is the dumped size
+    TableTooLarge(usize),
+    /// every code of the symbol table has a symbol, so none is left for the end of record marker
+    NoMarkerCode,
+}
+
+impl Display for EncodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            EncodeError::TableTooLarge(n) => write!(f, "symbol table dump of {} bytes is too large", n),
+            EncodeError::NoMarkerCode => write!(f, "no free code for the end of record marker"),
+        }
+    }
+}
+
+impl Error for EncodeError {}
@@ -0,0 +1,127 @@
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::core::{DEFAULT_SAMPLE_SEED, SAMPLE_TARGET, xorshift};
+use crate::core::symbol_table::{SymbolTable, SymbolTableBuilder};
+
+/// builds a symbol table from samples that arrive in batches
+/// only a reservoir of about `SAMPLE_TARGET` bytes of samples is kept, so the whole dataset never
+/// has to be buffered, and the table is rebuilt lazily once enough new data has been added
+pub struct IncrementalBuilder {
+    builder: SymbolTableBuilder,
+    samples: Vec<String>,
+    sample_bytes: usize,
+    // number of strings added so far, which decides the chance of replacing a reservoir sample
+    seen: usize,
+    // bytes added since the table was last built
+    pending_bytes: usize,
+    rebuild_bytes: usize,
+    sample_rand: u64,
+    table: Option<Box<dyn SymbolTable>>,
+}
+
+impl Default for IncrementalBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IncrementalBuilder {
+    pub fn new() -> IncrementalBuilder {
+        Self::with_builder(SymbolTableBuilder::new())
+    }
+
+    /// train the tables with the options of the given builder
+    pub fn with_builder(builder: SymbolTableBuilder) -> IncrementalBuilder {
+        IncrementalBuilder {
+            builder,
+            samples: Vec::new(),
+            sample_bytes: 0,
+            seen: 0,
+            pending_bytes: 0,
+            rebuild_bytes: SAMPLE_TARGET,
+            sample_rand: DEFAULT_SAMPLE_SEED,
+            table: None,
+        }
+    }
+
+    /// rebuild the table after this many bytes of new samples, `SAMPLE_TARGET` by default
+    pub fn rebuild_bytes(mut self, rebuild_bytes: usize) -> IncrementalBuilder {
+        self.rebuild_bytes = rebuild_bytes;
+        self
+    }
+
+    /// add a batch of samples, which rebuilds the table if enough new data has accumulated
+    pub fn add_samples(&mut self, batch: &[&str]) {
+        for str in batch {
+            self.seen += 1;
+            self.pending_bytes += str.len();
+            if self.sample_bytes < SAMPLE_TARGET {
+                self.sample_bytes += str.len();
+                self.samples.push(str.to_string());
+                continue;
+            }
+            // reservoir sampling, every string added so far is equally likely to be kept
+            self.sample_rand = xorshift(self.sample_rand);
+            let i = (self.sample_rand % self.seen as u64) as usize;
+            if i < self.samples.len() {
+                self.sample_bytes = self.sample_bytes - self.samples[i].len() + str.len();
+                self.samples[i] = str.to_string();
+            }
+        }
+        if self.pending_bytes >= self.rebuild_bytes {
+            self.rebuild();
+        }
+    }
+
+    /// the most recently built table, `None` until the first rebuild
+    #[allow(clippy::borrowed_box)]
+    pub fn table(&self) -> Option<&Box<dyn SymbolTable>> {
+        self.table.as_ref()
+    }
+
+    /// build the table from all samples added so far, reusing the current table if no samples were
+    /// added since it was built
+    pub fn finalize(mut self) -> Box<dyn SymbolTable> {
+        if self.table.is_none() || self.pending_bytes > 0 {
+            self.rebuild();
+        }
+        self.table.unwrap()
+    }
+
+    fn rebuild(&mut self) {
+        let samples = self.samples.iter().collect::<Vec<&String>>();
+        self.table = Some(self.builder.train_from_samples(&samples));
+        self.pending_bytes = 0;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::core::codec::{Decoder, Encoder};
+    use crate::core::incremental::IncrementalBuilder;
+    use crate::read_string_lines;
+
+    #[test]
+    pub fn test_incremental_builder() {
+        let strings = read_string_lines("assets/test_data/l_comment").unwrap();
+        let mut builder = IncrementalBuilder::new().rebuild_bytes(1 << 14);
+        assert!(builder.table().is_none());
+        for batch in strings.chunks(1000) {
+            let batch = batch.iter().map(|s| s.as_str()).collect::<Vec<&str>>();
+            builder.add_samples(&batch);
+        }
+        assert!(builder.table().is_some());
+        let symbol_table = builder.finalize();
+        assert!(!symbol_table.is_empty());
+        let encoder = Encoder::from_table(&symbol_table);
+        let decoder = Decoder::from_table(&symbol_table);
+        for str in strings.iter().take(1000) {
+            assert_eq!(*str, decoder.decode(&encoder.encode_str(str)));
+        }
+
+        let symbol_table = IncrementalBuilder::new().finalize();
+        assert!(symbol_table.is_empty());
+    }
+}
@@ -1,13 +1,23 @@
-use std::cmp::{max, min};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::{max, min};
 
 mod symbol;
 mod counter;
 pub mod symbol_table;
 pub mod codec;
+pub mod error;
+pub mod block;
+#[cfg(feature = "std")]
+pub mod stream;
+pub mod incremental;
 
 const U64_SIZE: usize = size_of::<u64>();
 const CODE_MAX: u16 = 1 << 9;
 const CODE_MASK: u16 = CODE_MAX - 1;
+// code space of tables using two-byte codes, codes below CODE_BASE are the single bytes
+const CODE_MAX_WIDE: u16 = 1 << 12;
+const CODE_MASK_WIDE: u16 = CODE_MAX_WIDE - 1;
 const CODE_BASE: u16 = 256;
 const CODE_ESCAPE: u8 = 255;
 const LEN_BITS: u16 = 12;
@@ -15,6 +25,24 @@ const HASH_SHIFT: usize = 15;
 const HASH_PRIME: usize = 2971215073;
 const SAMPLE_TARGET: usize = 1 << 16;
 const SMALL_STR_THRESHOLD: usize = 1 << 14;
+pub const DEFAULT_SAMPLE_SEED: u64 = 4637947;
+const TABLE_MAGIC: [u8; 4] = *b"FSST";
+const TABLE_FORMAT_VERSION: u8 = 2;
+// | magic | version | flags | endian |, followed by the length histogram
+const TABLE_HEADER_SIZE: usize = TABLE_MAGIC.len() + 3;
+// the table uses two-byte codes and the length histogram entries are two bytes
+const TABLE_FLAG_WIDE: u8 = 1;
+// the table has no symbols and the encodings are the input bytes stored verbatim
+const TABLE_FLAG_IDENTITY: u8 = 1 << 1;
+// the escape code is not 255 and follows the endian byte
+const TABLE_FLAG_ESCAPE: u8 = 1 << 2;
+// the symbols are not ordered by length, every symbol is dumped after a byte holding its length
+const TABLE_FLAG_UNSORTED: u8 = 1 << 3;
+// the length histogram is a byte with bit `i` set if there are symbols of length `i + 1`, followed
+// by only the entries of those lengths
+const TABLE_FLAG_COMPACT: u8 = 1 << 4;
+const TABLE_FLAGS: u8 =
+    TABLE_FLAG_WIDE | TABLE_FLAG_IDENTITY | TABLE_FLAG_ESCAPE | TABLE_FLAG_UNSORTED | TABLE_FLAG_COMPACT;
 
 type U64Bytes = [u8; U64_SIZE];
 
@@ -27,25 +55,44 @@ fn fsst_hash(v: usize) -> usize {
     prime ^ (prime >> HASH_SHIFT)
 }
 
+/// xorshift64 step, a zero state never advances so it should not be used as a seed
+pub(crate) fn xorshift(mut v: u64) -> u64 {
+    v ^= v << 13;
+    v ^= v >> 7;
+    v ^= v << 17;
+    v
+}
+
 fn bulk_load(s: &[u8]) -> u64 {
     let mut v = [0u8; U64_SIZE];
     v[..s.len()].copy_from_slice(s);
-    unsafe {
-        std::mem::transmute::<U64Bytes, u64>(v)
-    }
+    u64::from_ne_bytes(v)
 }
 
 fn bulk_load_u32(s: &[u8]) -> u32 {
     let mut v = [0u8; 4];
     v[..s.len()].copy_from_slice(s);
-    unsafe {
-        std::mem::transmute::<[u8; 4], u32>(v)
-    }
+    u32::from_ne_bytes(v)
 }
 
-pub fn take_sample(sample_space: &Vec<String>) -> Vec<&String> {
-    let total_size = sample_space.iter().map(|s| s.len()).sum::<usize>();
-    let (mut sample_size, mut sample_prob, mut sample_target) = (0usize, 256usize, SAMPLE_TARGET);
+pub fn take_sample<S: AsRef<str>>(sample_space: &[S]) -> Vec<&S> {
+    take_sample_with_seed(sample_space, DEFAULT_SAMPLE_SEED)
+}
+
+/// randomly sample strings using a deterministic pseudo-random sequence seeded by `seed`
+/// the same input and seed always yield the same sample
+pub fn take_sample_with_seed<S: AsRef<str>>(sample_space: &[S], seed: u64) -> Vec<&S> {
+    take_sample_with_target(sample_space, seed, SAMPLE_TARGET)
+}
+
+/// sample like `take_sample_with_seed` until the sample holds `sample_target` bytes
+fn take_sample_with_target<S: AsRef<str>>(sample_space: &[S], seed: u64, sample_target: usize) -> Vec<&S> {
+    let total_size = sample_space.iter().map(|s| s.as_ref().len()).sum::<usize>();
+    if total_size == 0 {
+        // only empty strings, which have nothing to learn from
+        return Vec::new();
+    }
+    let (mut sample_size, mut sample_prob, mut sample_target) = (0usize, 256usize, sample_target);
     if total_size > sample_target {
         sample_prob = max(4, 256 * sample_target / total_size);
     } else {
@@ -53,12 +100,13 @@ pub fn take_sample(sample_space: &Vec<String>) -> Vec<&String> {
     }
     let mut sample = Vec::with_capacity(sample_space.len() * (sample_target / total_size));
 
-    let sample_rand = 1;
+    let mut sample_rand = if seed == 0 { DEFAULT_SAMPLE_SEED } else { seed };
     while sample_size < sample_target {
         for str in sample_space {
-            if (sample_rand & 255) < sample_prob {
+            sample_rand = xorshift(sample_rand);
+            if ((sample_rand & 255) as usize) < sample_prob {
                 sample.push(str);
-                sample_size += str.len();
+                sample_size += str.as_ref().len();
                 if sample_size >= sample_target {
                     break;
                 }
@@ -70,12 +118,52 @@ pub fn take_sample(sample_space: &Vec<String>) -> Vec<&String> {
     sample
 }
 
+/// sample whole records by reservoir, unlike `take_sample_with_seed` every record is equally likely
+/// to be kept regardless of its position, which matters for sorted or clustered data
+/// records are drawn in a random order until the sample holds at least `target_bytes` bytes, and the
+/// sample keeps the order of the input
+pub fn take_sample_reservoir<S: AsRef<str>>(records: &[S], target_bytes: usize, seed: u64) -> Vec<&S> {
+    let total_size = records.iter().map(|s| s.as_ref().len()).sum::<usize>();
+    if total_size <= target_bytes {
+        return records.iter().filter(|s| !s.as_ref().is_empty()).collect();
+    }
+    let mut sample_rand = if seed == 0 { DEFAULT_SAMPLE_SEED } else { seed };
+    let mut keys = records
+        .iter()
+        .enumerate()
+        .map(|(i, _)| {
+            sample_rand = xorshift(sample_rand);
+            (sample_rand, i)
+        })
+        .collect::<Vec<(u64, usize)>>();
+    keys.sort_unstable();
+    let mut sample_size = 0;
+    let mut picked = Vec::new();
+    for (_, i) in keys {
+        if sample_size >= target_bytes {
+            break;
+        }
+        sample_size += records[i].as_ref().len();
+        picked.push(i);
+    }
+    picked.sort_unstable();
+    picked.into_iter().map(|i| &records[i]).collect()
+}
+
+/// sample one large buffer like the reference FSST does, by copying segments spread evenly over it
+/// the sample holds at most an eighth of the buffer and at most `SAMPLE_TARGET` bytes, a buffer
+/// shorter than 16 KB is returned whole
 pub fn take_sample_from_bytes(sample_space: &[u8]) -> Vec<u8> {
+    take_sample_from_bytes_with_target(sample_space, SAMPLE_TARGET)
+}
+
+/// sample like `take_sample_from_bytes` but with at most `sample_target` bytes
+fn take_sample_from_bytes_with_target(sample_space: &[u8], sample_target: usize) -> Vec<u8> {
     if sample_space.len() < SMALL_STR_THRESHOLD {
         return Vec::from(sample_space);
     }
 
-    let sample_size = min(sample_space.len() >> 3, SAMPLE_TARGET);
+    let sample_size = min(sample_space.len() >> 3, sample_target);
     let sample_seg_size = sample_size / 10;
     let mut sample = vec![0; sample_size];
     let gap = (sample_space.len() - sample.len()) / 10;
@@ -93,3 +181,75 @@ pub fn take_sample_from_bytes(sample_space: &[u8]) -> Vec<u8> {
     sample.truncate(pos_out);
     sample
 }
+
+
+#[cfg(test)]
+mod test {
+    use crate::core::{
+        SAMPLE_TARGET, SMALL_STR_THRESHOLD, take_sample_from_bytes, take_sample_from_bytes_with_target,
+        take_sample_reservoir, take_sample_with_seed, take_sample_with_target,
+    };
+
+    #[test]
+    pub fn test_take_sample_with_seed() {
+        let strings: Vec<String> = (0..10000).map(|i| format!("string-{}", i)).collect();
+        let sample = take_sample_with_seed(&strings, 42);
+        assert_eq!(sample, take_sample_with_seed(&strings, 42));
+        assert_ne!(sample, take_sample_with_seed(&strings, 43));
+        // the sample is not simply a prefix of the input
+        assert!(sample.iter().zip(strings.iter()).any(|(s, t)| *s != t));
+    }
+
+    #[test]
+    pub fn test_take_sample_from_empty_strings() {
+        let strings = vec![String::new(); 10];
+        assert!(take_sample_with_seed(&strings, 42).is_empty());
+        assert!(take_sample_with_seed::<String>(&[], 42).is_empty());
+    }
+
+    #[test]
+    pub fn test_take_sample_reservoir() {
+        // sorted data, a prefix sample would only see the earliest dates
+        let records: Vec<String> = (0..20000).map(|i| format!("2020-{:02}-{:05}", i / 2000 + 1, i)).collect();
+        let sample = take_sample_reservoir(&records, 1 << 14, 42);
+        assert_eq!(sample, take_sample_reservoir(&records, 1 << 14, 42));
+        let sample_size = sample.iter().map(|s| s.len()).sum::<usize>();
+        assert!(sample_size >= 1 << 14 && sample_size < (1 << 14) + records[0].len());
+        for month in ["2020-01", "2020-05", "2020-10"] {
+            assert!(sample.iter().any(|s| s.starts_with(month)));
+        }
+        // a sample in input order
+        assert!(sample.windows(2).all(|w| w[0] < w[1]));
+
+        let few = vec!["a", "", "b"];
+        assert_eq!(vec![&"a", &"b"], take_sample_reservoir(&few, 1 << 14, 42));
+    }
+
+    #[test]
+    pub fn test_take_sample_from_bytes() {
+        let data = std::fs::read("assets/test_data/l_comment").unwrap();
+        let sample = take_sample_from_bytes(&data);
+        assert!(!sample.is_empty() && sample.len() <= SAMPLE_TARGET);
+        // the segments are spread over the whole buffer instead of taken from its start
+        assert_ne!(&data[..sample.len()], sample.as_slice());
+        assert!(data.windows(64).any(|w| w == &sample[sample.len() - 64..]));
+
+        let sample = take_sample_from_bytes(&data[..SAMPLE_TARGET * 2]);
+        assert!(sample.len() <= SAMPLE_TARGET * 2 / 8);
+        assert_eq!(&data[..100], take_sample_from_bytes(&data[..100]).as_slice());
+        assert_eq!(SMALL_STR_THRESHOLD - 1, take_sample_from_bytes(&data[..SMALL_STR_THRESHOLD - 1]).len());
+    }
+
+    #[test]
+    pub fn test_take_sample_with_target() {
+        let strings: Vec<String> = (0..10000).map(|i| format!("string-{}", i)).collect();
+        assert_eq!(take_sample_with_seed(&strings, 42), take_sample_with_target(&strings, 42, SAMPLE_TARGET));
+        let sample_size = take_sample_with_target(&strings, 42, 1 << 10).iter().map(|s| s.len()).sum::<usize>();
+        assert!((1 << 10..(1 << 10) + 16).contains(&sample_size));
+
+        let data = std::fs::read("assets/test_data/l_comment").unwrap();
+        assert_eq!(take_sample_from_bytes(&data), take_sample_from_bytes_with_target(&data, SAMPLE_TARGET));
+        let sample = take_sample_from_bytes_with_target(&data, SAMPLE_TARGET * 4);
+        assert!(sample.len() > SAMPLE_TARGET && sample.len() <= SAMPLE_TARGET * 4);
+    }
+}
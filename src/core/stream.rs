@@ -0,0 +1,314 @@
+use std::cmp::min;
+use std::io;
+use std::io::{ErrorKind, Read, Write};
+
+use crate::core::codec::{Decoder, Encoder};
+use crate::core::symbol_table::SymbolTable;
+
+const RECORD_DELIMITER: u8 = b'\n';
+const READ_CHUNK_SIZE: usize = 8192;
+
+/// streaming encoder that implements `std::io::Write`
+/// incoming bytes are buffered and split into newline-terminated records, every complete record
+/// (including its newline) is encoded and written to the underlying writer as soon as it arrives.
+/// the encoded stream is the concatenation of the record encodings, so decoding it yields the
+/// original bytes.
+/// `finish` must be called to encode the final partial record.
+pub struct StreamEncoder<'a, W: Write> {
+    encoder: Encoder<'a>,
+    writer: W,
+    pending: Vec<u8>,
+    // the encoding of the last record, `buf[written..]` is not written to the writer yet
+    buf: Vec<u8>,
+    written: usize,
+}
+
+impl<'a, W: Write> StreamEncoder<'a, W> {
+    #[allow(clippy::borrowed_box)]
+    pub fn new(table: &'a Box<dyn SymbolTable>, writer: W) -> StreamEncoder<'a, W> {
+        StreamEncoder {
+            encoder: Encoder::from_table(table),
+            writer,
+            pending: Vec::new(),
+            buf: Vec::new(),
+            written: 0,
+        }
+    }
+
+    /// encode the buffered partial record, flush the underlying writer and return it
+    pub fn finish(mut self) -> io::Result<W> {
+        self.write_buf()?;
+        if !self.pending.is_empty() {
+            self.encoder.encode_into(&self.pending, &mut self.buf);
+            self.pending.clear();
+            self.write_buf()?;
+        }
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    /// write the rest of the encoded record, on an error the bytes the writer took are remembered
+    /// so the next call resumes after them instead of writing them again
+    fn write_buf(&mut self) -> io::Result<()> {
+        while self.written < self.buf.len() {
+            match self.writer.write(&self.buf[self.written..]) {
+                Ok(0) => return Err(io::Error::new(ErrorKind::WriteZero, "failed to write the encoded record")),
+                Ok(n) => self.written += n,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        self.buf.clear();
+        self.written = 0;
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for StreamEncoder<'_, W> {
+    /// a record is consumed once it is encoded, if the writer fails on its encoding the length up to
+    /// and including it is returned and the rest of the encoding is written by the next call, which
+    /// returns the error if it fails again before consuming anything
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.write_buf()?;
+        let mut start = 0;
+        while let Some(offset) = data[start..].iter().position(|&b| b == RECORD_DELIMITER) {
+            let end = start + offset + 1;
+            if self.pending.is_empty() {
+                self.encoder.encode_into(&data[start..end], &mut self.buf);
+            } else {
+                self.pending.extend_from_slice(&data[start..end]);
+                self.encoder.encode_into(&self.pending, &mut self.buf);
+                self.pending.clear();
+            }
+            start = end;
+            if self.write_buf().is_err() {
+                return Ok(start);
+            }
+        }
+        self.pending.extend_from_slice(&data[start..]);
+        Ok(data.len())
+    }
+
+    /// write the rest of an encoded record and flush the underlying writer, the buffered partial
+    /// record is kept until it is completed or `finish` is called
+    fn flush(&mut self) -> io::Result<()> {
+        self.write_buf()?;
+        self.writer.flush()
+    }
+}
+
+/// streaming decoder that implements `std::io::Read`
+/// encoded bytes are read from the source reader chunk by chunk and decoded on demand, decoded
+/// bytes that do not fit into the caller's buffer are kept until the next `read` call, so the
+/// memory usage is bounded by the chunk size.
+pub struct StreamDecoder<R: Read> {
+    decoder: Decoder,
+    reader: R,
+    input: Vec<u8>,
+    output: Vec<u8>,
+    output_pos: usize,
+}
+
+impl<R: Read> StreamDecoder<R> {
+    pub fn new(decoder: Decoder, reader: R) -> StreamDecoder<R> {
+        StreamDecoder {
+            decoder,
+            reader,
+            input: Vec::with_capacity(READ_CHUNK_SIZE + 1),
+            output: Vec::new(),
+            output_pos: 0,
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// read and decode the next chunk, returns false when the source reader is exhausted
+    fn fill(&mut self) -> io::Result<bool> {
+        // the input may still hold an escape code carried over from the previous chunk
+        let carried = self.input.len();
+        self.input.resize(carried + READ_CHUNK_SIZE, 0);
+        let n = loop {
+            match self.reader.read(&mut self.input[carried..]) {
+                Ok(n) => break n,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    self.input.truncate(carried);
+                    return Err(e);
+                }
+            }
+        };
+        self.input.truncate(carried + n);
+        if n == 0 {
+            // a lone escape code (or half of a wide code) at the end of the stream is dropped like
+            // `Decoder::decode` does
+            self.input.clear();
+            return Ok(false);
+        }
+
+        let end = if self.decoder.is_identity() {
+            self.input.len()
+        } else if self.decoder.is_wide() {
+            // only whole two-byte codes
+            self.input.len() & !1
+        } else {
+            Self::decodable_len(&self.input, self.decoder.escape_code())
+        };
+        self.decoder.decode_into(&self.input[..end], &mut self.output);
+        self.output_pos = 0;
+        self.input.drain(..end);
+        Ok(true)
+    }
+
+    /// length of the longest prefix that does not end with an escape code missing its escaped byte
+    fn decodable_len(input: &[u8], escape: u8) -> usize {
+        let mut pos = 0;
+        while pos < input.len() {
+            pos += if input[pos] == escape { 2 } else { 1 };
+        }
+        if pos > input.len() {
+            input.len() - 1
+        } else {
+            input.len()
+        }
+    }
+}
+
+impl<R: Read> Read for StreamDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.output_pos == self.output.len() {
+            if !self.fill()? {
+                return Ok(0);
+            }
+        }
+        let n = min(buf.len(), self.output.len() - self.output_pos);
+        buf[..n].copy_from_slice(&self.output[self.output_pos..self.output_pos + n]);
+        self.output_pos += n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io;
+    use std::io::{Read, Write};
+
+    use crate::core::codec::{Decoder, Encoder};
+    use crate::core::stream::{StreamDecoder, StreamEncoder};
+    use crate::core::symbol_table::SymbolTableBuilder;
+
+    #[test]
+    pub fn test_stream_encoder() {
+        let data = std::fs::read("assets/test_data/ps_comment").unwrap();
+        let symbol_table = SymbolTableBuilder::build_from(&String::from_utf8_lossy(&data[..4096]));
+        let mut stream_encoder = StreamEncoder::new(&symbol_table, Vec::new());
+        for chunk in data.chunks(100) {
+            stream_encoder.write_all(chunk).unwrap();
+        }
+        stream_encoder.write_all(b"partial record").unwrap();
+        let encoding = stream_encoder.finish().unwrap();
+
+        let encoder = Encoder::from_table(&symbol_table);
+        let mut expected = Vec::new();
+        for line in data.split_inclusive(|&b| b == b'\n') {
+            expected.append(&mut encoder.encode_bytes(line));
+        }
+        expected.append(&mut encoder.encode_bytes(b"partial record"));
+        assert_eq!(expected, encoding);
+
+        let mut original = data.clone();
+        original.extend_from_slice(b"partial record");
+        assert_eq!(original, Decoder::from_table(&symbol_table).decode_bytes(&encoding));
+    }
+
+    /// takes at most `max_write` bytes per call and fails every call after `writes` of them
+    struct FailingWriter {
+        written: Vec<u8>,
+        writes: usize,
+        max_write: usize,
+    }
+
+    impl Write for FailingWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.writes == 0 {
+                return Err(io::Error::other("write failed"));
+            }
+            self.writes -= 1;
+            let n = buf.len().min(self.max_write);
+            self.written.extend_from_slice(&buf[..n]);
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    pub fn test_stream_encoder_write_error() {
+        let symbol_table = SymbolTableBuilder::build_from("tumcwitumvldb\n");
+        let encoder = Encoder::from_table(&symbol_table);
+        let writer = FailingWriter { written: Vec::new(), writes: 1, max_write: usize::MAX };
+        let mut stream_encoder = StreamEncoder::new(&symbol_table, writer);
+        stream_encoder.write_all(b"tum").unwrap();
+        // the first record is written, the second is consumed but its encoding fails to be written
+        assert_eq!(9, stream_encoder.write(b"cwi\nvldb\nx").unwrap());
+        assert_eq!(encoder.encode_bytes(b"tumcwi\n"), stream_encoder.writer.written);
+        // nothing is consumed while the encoding of the last record can not be written
+        assert!(stream_encoder.write(b"x\n").is_err());
+        assert!(stream_encoder.flush().is_err());
+        stream_encoder.writer.writes = usize::MAX;
+        stream_encoder.write_all(b"x\n").unwrap();
+        let written = stream_encoder.finish().unwrap().written;
+        let expected = [&b"tumcwi\n"[..], b"vldb\n", b"x\n"].map(|record| encoder.encode_bytes(record)).concat();
+        assert_eq!(expected, written);
+    }
+
+    #[test]
+    pub fn test_stream_encoder_short_write() {
+        let data = b"tumcwitumvldb\nvldbtumcwi\ntum\n";
+        let symbol_table = SymbolTableBuilder::build_from("tumcwitumvldb\n");
+        let encoder = Encoder::from_table(&symbol_table);
+        // the writer takes one byte of the first encoding and then fails
+        let writer = FailingWriter { written: Vec::new(), writes: 1, max_write: 1 };
+        let mut stream_encoder = StreamEncoder::new(&symbol_table, writer);
+        assert_eq!(14, stream_encoder.write(data).unwrap());
+        assert_eq!(1, stream_encoder.writer.written.len());
+        // the retry resumes after the written byte instead of encoding the record again
+        stream_encoder.writer.writes = usize::MAX;
+        stream_encoder.write_all(&data[14..]).unwrap();
+        let written = stream_encoder.finish().unwrap().written;
+        let expected = data
+            .split_inclusive(|&b| b == b'\n')
+            .flat_map(|record| encoder.encode_bytes(record))
+            .collect::<Vec<u8>>();
+        assert_eq!(expected, written);
+        assert_eq!(data.to_vec(), Decoder::from_table(&symbol_table).decode_bytes(&written));
+    }
+
+    #[test]
+    pub fn test_stream_decoder() {
+        let mut data = std::fs::read("assets/test_data/l_comment").unwrap();
+        data.extend_from_slice(&[0xff; 100]);
+        let symbol_table = SymbolTableBuilder::build_from(&String::from_utf8_lossy(&data[..4096]));
+        let encoding = Encoder::from_table(&symbol_table).encode_bytes(&data);
+        let mut stream_decoder = StreamDecoder::new(Decoder::from_table(&symbol_table), encoding.as_slice());
+        let mut decoded = Vec::new();
+        let mut buf = [0u8; 3];
+        loop {
+            let n = stream_decoder.read(&mut buf).unwrap();
+            if n == 0 {
+                break;
+            }
+            decoded.extend_from_slice(&buf[..n]);
+        }
+        assert_eq!(data, decoded);
+
+        assert_eq!(1, StreamDecoder::<&[u8]>::decodable_len(&[1, 255], 255));
+        assert_eq!(2, StreamDecoder::<&[u8]>::decodable_len(&[255, 255], 255));
+        assert_eq!(2, StreamDecoder::<&[u8]>::decodable_len(&[255, 255, 255], 255));
+        assert_eq!(1, StreamDecoder::<&[u8]>::decodable_len(&[0, 255], 255));
+        assert_eq!(1, StreamDecoder::<&[u8]>::decodable_len(&[255, 0], 0));
+    }
+}
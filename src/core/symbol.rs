@@ -1,9 +1,11 @@
-use std::cmp::{min, Ordering};
-use std::fmt::{Display, Formatter};
-use std::hash::{Hash, Hasher};
-use std::ops::Add;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cmp::{min, Ordering};
+use core::fmt::{Display, Formatter};
+use core::hash::{Hash, Hasher};
+use core::ops::Add;
 
-use crate::core::{CODE_MASK, fsst_hash, U64_SIZE, U64Bytes};
+use crate::core::{CODE_MASK_WIDE, fsst_hash, U64_SIZE, U64Bytes};
 
 #[derive(Clone, Copy)]
 pub struct Symbol {
@@ -13,19 +15,19 @@ pub struct Symbol {
 
 impl Symbol {
     pub(crate) const MAX_LEN: usize = U64_SIZE;
-    const FREE_ICL: u64 = ((15 << 28) | ((CODE_MASK as u32) << 16)) as u64;
+    const FREE_ICL: u64 = ((15 << 28) | ((CODE_MASK_WIDE as u32) << 16)) as u64;
 
     pub fn from_str(str: &str) -> Symbol {
-        Self::from_str_bytes(str.as_bytes())
+        Self::from_bytes(str.as_bytes())
     }
 
-    pub fn from_str_bytes(bytes: &[u8]) -> Symbol {
+    pub fn from_bytes(bytes: &[u8]) -> Symbol {
         let len = min(bytes.len(), Self::MAX_LEN);
         let mut str_bytes = [0u8; Self::MAX_LEN];
         str_bytes[..len].copy_from_slice(&bytes[..len]);
         Symbol {
             num: Self::bytes_to_u64(str_bytes),
-            icl: Self::compute_icl(CODE_MASK as u32, len as u32),
+            icl: Self::compute_icl(CODE_MASK_WIDE as u32, len as u32),
         }
     }
 
@@ -39,7 +41,7 @@ impl Symbol {
     pub fn from_str_num(num: u64, len: usize) -> Symbol {
         Symbol {
             num,
-            icl: Self::compute_icl(CODE_MASK as u32, len as u32),
+            icl: Self::compute_icl(CODE_MASK_WIDE as u32, len as u32),
         }
     }
 
@@ -55,7 +57,7 @@ impl Symbol {
     }
 
     pub fn code(&self) -> u16 {
-        ((self.icl >> 16) & CODE_MASK as u64) as u16
+        ((self.icl >> 16) & CODE_MASK_WIDE as u64) as u16
     }
 
     pub fn compute_icl(code: u32, len: u32) -> u64 {
@@ -92,6 +94,26 @@ impl Symbol {
         self.num
     }
 
+    /// the bytes of the symbol
+    pub fn bytes(&self) -> Vec<u8> {
+        let (bytes, len) = self.to_bytes();
+        bytes[..len].to_vec()
+    }
+
+    /// the bytes of the symbol without allocating, only the first `len` bytes are significant
+    pub fn to_bytes(self) -> (U64Bytes, usize) {
+        (Self::u64_to_bytes(self.num), self.length())
+    }
+
+    /// the concatenation of the two symbols, or `None` if it would be longer than `MAX_LEN`, where
+    /// `+` keeps only the first `MAX_LEN` bytes
+    pub fn checked_add(self, rhs: Symbol) -> Option<Symbol> {
+        if self.length() + rhs.length() > Self::MAX_LEN {
+            return None;
+        }
+        Some(self + rhs)
+    }
+
     pub fn first(&self) -> usize {
         (self.num & 0xff) as usize
     }
@@ -105,22 +127,18 @@ impl Symbol {
     }
 
     fn bytes_to_u64(str_bytes: U64Bytes) -> u64 {
-        unsafe {
-            std::mem::transmute::<U64Bytes, u64>(str_bytes)
-        }
+        u64::from_ne_bytes(str_bytes)
     }
 
     fn u64_to_bytes(value: u64) -> U64Bytes {
-        unsafe {
-            std::mem::transmute::<u64, U64Bytes>(value)
-        }
+        value.to_ne_bytes()
     }
 }
 
 impl Display for Symbol {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let s = Self::u64_to_bytes(self.num.clone());
-        write!(f, "{}", String::from_utf8_lossy(&s[0..self.length()]))
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        let (bytes, len) = self.to_bytes();
+        write!(f, "{}", String::from_utf8_lossy(&bytes[..len]))
     }
 }
 
@@ -138,8 +156,8 @@ impl Add for Symbol {
         let this_len = self.length();
         let concat_len = min(this_len + rhs.length(), Symbol::MAX_LEN);
         Symbol {
-            num: (&rhs.num << (8 * this_len)) | &self.num,
-            icl: Self::compute_icl(CODE_MASK as u32, concat_len as u32),
+            num: (rhs.num << (8 * this_len)) | self.num,
+            icl: Self::compute_icl(CODE_MASK_WIDE as u32, concat_len as u32),
         }
     }
 }
@@ -154,13 +172,14 @@ impl Eq for Symbol {}
 
 impl PartialOrd<Self> for Symbol {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        self.num.partial_cmp(&other.num)
+        Some(self.cmp(other))
     }
 }
 
 impl Ord for Symbol {
     fn cmp(&self, other: &Self) -> Ordering {
-        self.num.cmp(&other.num)
+        // the length breaks ties, a symbol can end with zero bytes which do not change `num`
+        self.num.cmp(&other.num).then(self.length().cmp(&other.length()))
     }
 }
 
@@ -173,9 +192,27 @@ mod test {
         let s1 = Symbol::from_str("1234");
         assert_eq!("1234", s1.clone().to_string());
         let s2 = Symbol::from_str("567");
-        assert_eq!("1234567", (s1.clone() + s2).to_string());
+        assert_eq!("1234567", (s1 + s2).to_string());
         let s3 = Symbol::from_str("56789");
-        assert_ne!("123456789", (s1.clone() + s3.clone()).to_string());
+        assert_ne!("123456789", (s1 + s3).to_string());
         assert_eq!("12345678", (s1 + s3).to_string());
     }
-}
\ No newline at end of file
+
+    #[test]
+    pub fn test_symbol_checked_add() {
+        let s1 = Symbol::from_str("1234");
+        assert_eq!(Some("1234567".to_string()), s1.checked_add(Symbol::from_str("567")).map(|s| s.to_string()));
+        assert_eq!(Some("12345678".to_string()), s1.checked_add(Symbol::from_str("5678")).map(|s| s.to_string()));
+        assert!(s1.checked_add(Symbol::from_str("56789")).is_none());
+        assert!(Symbol::from_str("12345678").checked_add(Symbol::from_str("9")).is_none());
+    }
+
+    #[test]
+    pub fn test_to_bytes() {
+        let (bytes, len) = Symbol::from_str("tumc").to_bytes();
+        assert_eq!(b"tumc", &bytes[..len]);
+        let (bytes, len) = Symbol::from_bytes(&[b'a', 0, 0]).to_bytes();
+        assert_eq!(&[b'a', 0, 0], &bytes[..len]);
+        assert_eq!(vec![0xffu8], Symbol::from_byte_code(0xff, 0).bytes());
+    }
+}
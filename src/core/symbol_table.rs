@@ -1,20 +1,142 @@
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::{min, Reverse};
+use core::fmt::{Display, Formatter};
+#[cfg(feature = "std")]
 use std::collections::HashMap;
-use std::fmt::{Display, Formatter};
 
-use crate::core::{CODE_BASE, CODE_MASK, CODE_MAX, fsst_hash, is_escape_code, LEN_BITS};
+use crate::core::{
+    CODE_BASE, CODE_ESCAPE, CODE_MASK, CODE_MASK_WIDE, CODE_MAX, CODE_MAX_WIDE, DEFAULT_SAMPLE_SEED,
+    fsst_hash, is_escape_code, LEN_BITS, SAMPLE_TARGET, TABLE_FLAG_COMPACT, TABLE_FLAG_ESCAPE, TABLE_FLAG_IDENTITY,
+    TABLE_FLAG_UNSORTED, TABLE_FLAG_WIDE, TABLE_FLAGS,
+    TABLE_FORMAT_VERSION, TABLE_HEADER_SIZE, TABLE_MAGIC, take_sample_from_bytes_with_target, take_sample_reservoir,
+    take_sample_with_target, U64_SIZE,
+};
+use crate::core::codec::{code_byte, Encoder};
 use crate::core::counter::Counter;
+use crate::core::error::DecodeError;
 use crate::core::symbol::Symbol;
 use crate::util::endian::Endian;
 
-pub trait SymbolTable: SymbolTableClone + Display {
+pub trait SymbolTable: SymbolTableClone + Display + Send + Sync {
     fn add(&mut self, s: Symbol) -> bool;
     fn find_longest_symbol_code(&self, str_bytes: &[u8]) -> u16;
     fn get_symbol(&self, code: u16) -> &Symbol;
-    fn encode_for(&self, target: &Symbol) -> (u8, usize, usize);
+    fn encode_for(&self, target: &Symbol) -> (u16, usize, usize);
     fn len(&self) -> usize;
+    /// whether the table has not learned any symbol
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
     fn clear(&mut self);
     fn finalize(&mut self);
-    fn dump(&self) -> Vec<u8>;
+    fn dump(&self) -> Vec<u8> {
+        self.dump_with_endian(Endian::get_native_endian())
+    }
+    /// append the dump to the given buffer instead of allocating a new one, so many tables can be
+    /// written into one shared buffer
+    fn dump_into(&self, out: &mut Vec<u8>) {
+        self.dump_with_endian_into(Endian::get_native_endian(), out)
+    }
+    /// dump the table with the given byte order instead of the native one, so the dump is
+    /// byte-identical across platforms
+    fn dump_with_endian(&self, endian: Endian) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(self.dump_len());
+        self.dump_with_endian_into(endian, &mut buf);
+        buf
+    }
+    /// append the dump with the given byte order to the given buffer
+    fn dump_with_endian_into(&self, endian: Endian, out: &mut Vec<u8>);
+    /// dump the table with a length histogram that only holds the lengths having symbols, which
+    /// saves up to 7 bytes (14 with two-byte codes) for small tables inlined with every record
+    /// `SymbolTableBuilder::load_from_bytes` and `Decoder::from_table_bytes` read both dumps
+    fn dump_compact(&self) -> Vec<u8> {
+        let symbols = self.iter_symbols().map(|(_, bytes)| Symbol::from_bytes(&bytes)).collect::<Vec<Symbol>>();
+        let mut flags = TABLE_FLAG_COMPACT;
+        if self.is_wide() {
+            flags |= TABLE_FLAG_WIDE;
+        }
+        if self.is_identity() {
+            flags |= TABLE_FLAG_IDENTITY;
+        }
+        if symbols.windows(2).any(|w| w[0].length() > w[1].length()) {
+            flags |= TABLE_FLAG_UNSORTED;
+        }
+        let mut buf = Vec::new();
+        let len_histo = self.length_histogram();
+        dump_symbols(flags, Endian::get_native_endian(), self.escape_code(), &len_histo, symbols.iter(), &mut buf);
+        buf
+    }
+    /// length of the dump, computed without dumping
+    fn dump_len(&self) -> usize {
+        dump_len(self.is_wide(), self.escape_code(), &self.length_histogram())
+    }
+    /// whether the table encodes every symbol with a two-byte code instead of a one-byte code or
+    /// an escaped byte
+    fn is_wide(&self) -> bool {
+        false
+    }
+    /// the byte written before an escaped byte, the codes of the symbols skip it
+    fn escape_code(&self) -> u8 {
+        CODE_ESCAPE
+    }
+    /// whether the table stores the input verbatim instead of encoding it, see
+    /// `SymbolTableBuilder::identity_table`
+    fn is_identity(&self) -> bool {
+        false
+    }
+    /// iterate the learned symbols in code order, yielding each code with the bytes it stands for
+    fn iter_symbols(&self) -> Box<dyn Iterator<Item = (u16, Vec<u8>)> + '_>;
+    /// the learned symbols as owned pairs of their bytes and the code written for them, for
+    /// consumers that are not FSST decoders
+    /// the codes of a table with one-byte codes are the bytes in the encoding, which skip the escape
+    /// code, the single bytes of a table with two-byte codes are not listed
+    fn to_dictionary(&self) -> Vec<(Vec<u8>, u16)> {
+        let (wide, escape) = (self.is_wide(), self.escape_code());
+        self.iter_symbols()
+            .map(|(code, bytes)| (bytes, if wide { code } else { code_byte(code, escape) as u16 }))
+            .collect()
+    }
+    /// number of symbols of each length, the count of length `i + 1` is at index `i`
+    fn length_histogram(&self) -> [usize; Symbol::MAX_LEN];
+    /// the bytes of the learned symbols grouped by length, the symbols of length `i + 1` are at
+    /// index `i` in code order
+    fn symbols_by_length(&self) -> [Vec<Vec<u8>>; Symbol::MAX_LEN] {
+        let mut buckets: [Vec<Vec<u8>>; Symbol::MAX_LEN] = Default::default();
+        for (_, bytes) in self.iter_symbols() {
+            buckets[bytes.len() - 1].push(bytes);
+        }
+        buckets
+    }
+    /// add a symbol of 1 to 8 bytes to a finalized table, keeping the codes of the symbols it holds,
+    /// so encodings written before still decode, which lets a loaded table grow with hot strings
+    /// returns the code of the symbol as `iter_symbols` lists it, which is its current code if the
+    /// table holds it already, or `None` if the symbol does not fit
+    fn try_add_symbol(&mut self, _bytes: &[u8]) -> Option<u16> {
+        None
+    }
+    /// remove the learned symbols that are never emitted when encoding the corpus and rebuild the
+    /// finalized table from the others, which shrinks the dump
+    /// the corpus still encodes to the same length, only the codes are renumbered
+    /// returns the number of removed symbols
+    fn prune_unused(&mut self, corpus: &[&[u8]]) -> usize;
+    /// estimate the compression factor on the sample by running the encode loop without writing
+    /// the output, an empty sample gives 1.0
+    fn estimate_compression_factor(&self, sample: &[u8]) -> f64 {
+        let encoded_len = Encoder::from_table_ref(self).encoded_len(sample);
+        if encoded_len == 0 {
+            return 1.0;
+        }
+        sample.len() as f64 / encoded_len as f64
+    }
+    /// the table with one-byte codes behind the trait object, so the encoder can match input words
+    /// against its lookup tables without a dynamic call per code
+    fn as_perfect_hash(&self) -> Option<&PerfectHashSymbolTable> {
+        None
+    }
 }
 
 pub trait SymbolTableClone {
@@ -32,19 +154,46 @@ impl<T: Clone + SymbolTable> SymbolTableClone for T {
     }
 }
 
-#[derive(Clone, Copy)]
-struct PerfectHashSymbolTable {
+/// symbol table using one-byte codes, with an escape code (255 by default) escaping the byte that
+/// follows it
+/// a table can be built by hand with `new` and `add_bytes`, but `finalize` must be called after
+/// the last symbol is added and before the table is used for encoding, dumping or decoding
+/// the large lookup tables live on the heap, so a table can be built on threads with small stacks
+#[derive(Clone)]
+pub struct PerfectHashSymbolTable {
     // lookup table (only used during symbolTable construction, not during normal text compression)
     byte_codes: [u16; CODE_BASE as usize],
 
     // lookup table using the next two bytes (65536 codes), or just the next single byte
-    short_codes: [u16; 65536],
+    short_codes: Box<[u16; 65536]>,
 
-    hash_table: [Symbol; PerfectHashSymbolTable::TABLE_SIZE],
+    hash_table: Box<[Symbol; PerfectHashSymbolTable::TABLE_SIZE]>,
     symbols: [Symbol; CODE_MAX as usize],
     len_histo: [u8; Symbol::MAX_LEN],
     symbol_num: u16,
     finalized: bool,
+    // false if the table was finalized keeping the symbols in the order they were added
+    length_ordered: bool,
+    escape: u8,
+}
+
+/// tables are equal if they hold the same symbols under the same codes and escape with the same
+/// byte, the lookup tables are not compared since building the same symbols in another way can leave
+/// different entries in them
+impl PartialEq for PerfectHashSymbolTable {
+    fn eq(&self, other: &Self) -> bool {
+        self.finalized == other.finalized
+            && self.escape == other.escape
+            && self.learned_symbols() == other.learned_symbols()
+    }
+}
+
+impl Eq for PerfectHashSymbolTable {}
+
+impl Default for PerfectHashSymbolTable {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl PerfectHashSymbolTable {
@@ -60,13 +209,10 @@ impl PerfectHashSymbolTable {
             symbols[i as usize] = Symbol::from_byte_code(i as u8, byte_code);
         }
 
-        let mut short_codes = [0u16; 65536];
-        for i in 0..short_codes.len() {
-            short_codes[i] = (1 << LEN_BITS) | ((i as u16) & 0xff);
-        }
+        let short_codes = boxed_array((0..65536).map(|i| (1 << LEN_BITS) | ((i as u16) & 0xff)).collect());
 
         let len_histo = [0u8; Symbol::MAX_LEN];
-        let hash_table = [Symbol::free(); PerfectHashSymbolTable::TABLE_SIZE];
+        let hash_table = boxed_array(vec![Symbol::free(); PerfectHashSymbolTable::TABLE_SIZE]);
         PerfectHashSymbolTable {
             byte_codes,
             short_codes,
@@ -75,7 +221,93 @@ impl PerfectHashSymbolTable {
             len_histo,
             symbol_num: 0,
             finalized: false,
+            length_ordered: true,
+            escape: CODE_ESCAPE,
+        }
+    }
+
+    /// use another escape code than 255, which is stored in the dump
+    /// the encoded symbol codes skip the escape code, so the code bytes stay unambiguous
+    pub fn set_escape_code(&mut self, escape: u8) {
+        self.escape = escape;
+    }
+
+    /// add a symbol of 1 to 8 bytes
+    /// returns false if the symbol is not added, which happens when the table is full or already
+    /// finalized, or when a symbol of 3 or more bytes collides in the hash table
+    pub fn add_bytes(&mut self, bytes: &[u8]) -> bool {
+        if self.finalized || bytes.is_empty() || bytes.len() > Symbol::MAX_LEN || self.len() >= CODE_ESCAPE as usize {
+            return false;
+        }
+        self.add(Symbol::from_bytes(bytes))
+    }
+
+    /// finalize the table like `finalize`, but keep the codes in the order the symbols were added
+    /// instead of ordering them by length, so the symbols added first take the lowest codes
+    /// the dump then stores the length of every symbol, which readers before this flag reject
+    pub fn finalize_in_order(&mut self) {
+        self.finalize_codes(false);
+    }
+
+    /// move the learned symbols to the low codes, ordered by length or in the order they were added
+    fn finalize_codes(&mut self, by_length: bool) {
+        // compute running sum of code lengths (starting offsets for each length)
+        let mut rsum = [0u8; Symbol::MAX_LEN];
+        for i in 0..rsum.len() - 1 {
+            rsum[i + 1] = rsum[i] + self.len_histo[i];
+        }
+
+        let mut new_codes = [0u8; CODE_BASE as usize];
+        for i in CODE_BASE..CODE_BASE + self.symbol_num {
+            let mut s = self.symbols[i as usize];
+            let len = s.length();
+            new_codes[(i - CODE_BASE) as usize] = if by_length {
+                rsum[len - 1] += 1;
+                rsum[len - 1] - 1
+            } else {
+                (i - CODE_BASE) as u8
+            };
+            let new_code = new_codes[(i - CODE_BASE) as usize];
+            s.set_code_len(new_code as u16, len);
+            self.symbols[new_code as usize] = s;
+        }
+
+        for i in 0..CODE_BASE as usize {
+            if (self.byte_codes[i] & CODE_MASK) >= CODE_BASE {
+                let idx = (self.byte_codes[i] & 0xff) as usize;
+                self.byte_codes[i] = new_codes[idx] as u16 | (1 << LEN_BITS);
+            } else {
+                self.byte_codes[i] = CODE_MASK | (1 << LEN_BITS);
+            }
+        }
+
+        for i in 0..self.short_codes.len() {
+            if (self.short_codes[i] & CODE_MASK) >= CODE_BASE {
+                let idx = (self.short_codes[i] & 0xff) as usize;
+                self.short_codes[i] = new_codes[idx] as u16 | (self.short_codes[i] & (0xf << LEN_BITS));
+            } else {
+                self.short_codes[i] = self.byte_codes[i & 0xff];
+            }
+        }
+
+        for i in 0..self.hash_table.len() {
+            if self.hash_table[i].taken() {
+                let idx = (self.hash_table[i].code() & 0xff) as usize;
+                self.hash_table[i] = self.symbols[new_codes[idx] as usize];
+            }
         }
+        self.finalized = true;
+        self.length_ordered = by_length;
+    }
+
+    /// the learned symbols, which move to the low codes once the table is finalized
+    fn learned_symbols(&self) -> &[Symbol] {
+        let (start, end) = if self.finalized {
+            (0usize, self.symbol_num as usize)
+        } else {
+            (CODE_BASE as usize, (CODE_BASE + self.symbol_num) as usize)
+        };
+        &self.symbols[start..end]
     }
 
     fn hash_insert(&mut self, s: &Symbol) -> bool {
@@ -85,7 +317,7 @@ impl PerfectHashSymbolTable {
         }
 
         src_symbol.update_to(s);
-        return true;
+        true
     }
 
     fn get_hash_symbol_mut(&mut self, hash_value: usize) -> &mut Symbol {
@@ -99,6 +331,26 @@ impl PerfectHashSymbolTable {
     fn hash_idx(hash_value: usize) -> usize {
         hash_value & (PerfectHashSymbolTable::TABLE_SIZE - 1)
     }
+
+    /// the code of the longest symbol at the start of the word, which holds `len` valid bytes in
+    /// native byte order, the bytes past `len` are ignored
+    /// returns the code, the number of bytes it covers and whether it is an escaped byte, like
+    /// `encode_for` but without building a symbol
+    #[inline(always)]
+    pub(crate) fn encode_word(&self, word: u64, len: usize) -> (u16, usize, bool) {
+        let word = if len < U64_SIZE { word & ((1 << (8 * len)) - 1) } else { word };
+        let src_symbol = &self.hash_table[Self::hash_idx(fsst_hash((word & 0xffffff) as usize))];
+        // a symbol ending with zero bytes must not match the zero padding past the input
+        if src_symbol.length() <= len && src_symbol.word_match(word) {
+            return (src_symbol.code() & 0xff, src_symbol.length(), false);
+        }
+        let code = if len >= 2 {
+            self.short_codes[(word & 0xffff) as usize]
+        } else {
+            self.byte_codes[(word & 0xff) as usize]
+        };
+        (code & 0xff, (code >> LEN_BITS) as usize, code & CODE_BASE != 0)
+    }
 }
 
 impl SymbolTable for PerfectHashSymbolTable {
@@ -117,11 +369,11 @@ impl SymbolTable for PerfectHashSymbolTable {
         self.symbols[code as usize] = s;
         self.symbol_num += 1;
         self.len_histo[len - 1] += 1;
-        return true;
+        true
     }
 
     fn find_longest_symbol_code(&self, str_bytes: &[u8]) -> u16 {
-        let target_symbol = Symbol::from_str_bytes(str_bytes);
+        let target_symbol = Symbol::from_bytes(str_bytes);
         let src_symbol = self.get_hash_symbol(target_symbol.hash());
         if target_symbol.prefix_match(src_symbol) {
             return src_symbol.code();
@@ -141,16 +393,21 @@ impl SymbolTable for PerfectHashSymbolTable {
         &self.symbols[code as usize]
     }
 
-    fn encode_for(&self, target: &Symbol) -> (u8, usize, usize) {
+    fn encode_for(&self, target: &Symbol) -> (u16, usize, usize) {
         let src_symbol = self.get_hash_symbol(target.hash());
         if target.prefix_match(src_symbol) {
-            return (src_symbol.code() as u8, src_symbol.length(), 1);
+            return (src_symbol.code() & 0xff, src_symbol.length(), 1);
         }
 
-        let code = self.short_codes[target.first2()];
+        // a single trailing byte must not match a two-byte symbol ending with a zero byte
+        let code = if target.length() >= 2 {
+            self.short_codes[target.first2()]
+        } else {
+            self.byte_codes[target.first()]
+        };
         let s_len = (code >> LEN_BITS) as usize;
         let out_len = (1 + ((code & CODE_BASE) >> 8)) as usize;
-        (code as u8, s_len, out_len)
+        (code & 0xff, s_len, out_len)
     }
 
     fn len(&self) -> usize {
@@ -180,78 +437,81 @@ impl SymbolTable for PerfectHashSymbolTable {
     }
 
     fn finalize(&mut self) {
-        // compute running sum of code lengths (starting offsets for each length)
-        let mut rsum = [0u8; Symbol::MAX_LEN];
-        for i in 0..rsum.len() - 1 {
-            rsum[i + 1] = rsum[i] + self.len_histo[i];
-        }
+        self.finalize_codes(true);
+    }
 
-        let mut new_codes = [0u8; CODE_BASE as usize];
-        for i in CODE_BASE..CODE_BASE + self.symbol_num {
-            let mut s = self.symbols[i as usize];
-            let len = s.length();
-            new_codes[(i - CODE_BASE) as usize] = rsum[len - 1];
-            rsum[len - 1] += 1;
-            let new_code = new_codes[(i - CODE_BASE) as usize];
-            s.set_code_len(new_code as u16, len);
-            self.symbols[new_code as usize] = s;
-        }
+    fn as_perfect_hash(&self) -> Option<&PerfectHashSymbolTable> {
+        Some(self)
+    }
 
-        for i in 0..CODE_BASE as usize {
-            if (self.byte_codes[i] & CODE_MASK) >= CODE_BASE {
-                let idx = (self.byte_codes[i] & 0xff) as usize;
-                self.byte_codes[i] = new_codes[idx] as u16 | (1 << LEN_BITS);
-            } else {
-                self.byte_codes[i] = CODE_MASK | (1 << LEN_BITS);
-            }
+    /// the table is rebuilt with the new symbol taking the next code and finalized in order, see
+    /// `finalize_in_order`, unless the codes stay ordered by length
+    /// an unfinalized table is finalized in the order its symbols were added
+    fn try_add_symbol(&mut self, bytes: &[u8]) -> Option<u16> {
+        if bytes.is_empty() || bytes.len() > Symbol::MAX_LEN {
+            return None;
         }
-
-        for i in 0..self.short_codes.len() {
-            if (self.short_codes[i] & CODE_MASK) >= CODE_BASE {
-                let idx = (self.short_codes[i] & 0xff) as usize;
-                self.short_codes[i] = new_codes[idx] as u16 | (self.short_codes[i] & (0xf << LEN_BITS));
-            } else {
-                self.short_codes[i] = self.byte_codes[i & 0xff];
-            }
+        if let Some((code, _)) = self.iter_symbols().find(|(_, symbol)| symbol.as_slice() == bytes) {
+            return Some(code);
         }
-
-        for i in 0..self.hash_table.len() {
-            if self.hash_table[i].taken() {
-                let idx = (self.hash_table[i].code() & 0xff) as usize;
-                self.hash_table[i] = self.symbols[new_codes[idx] as usize];
-            }
+        if self.len() >= CODE_ESCAPE as usize {
+            return None;
         }
-        self.finalized = true;
+        let mut symbol_table = PerfectHashSymbolTable::new();
+        symbol_table.set_escape_code(self.escape);
+        for s in self.learned_symbols() {
+            symbol_table.add(*s);
+        }
+        let longest = self.learned_symbols().iter().map(|s| s.length()).max().unwrap_or(0);
+        if !symbol_table.add(Symbol::from_bytes(bytes)) {
+            // a symbol of 3 or more bytes colliding in the hash table
+            return None;
+        }
+        symbol_table.finalize_codes(self.finalized && self.length_ordered && bytes.len() >= longest);
+        *self = symbol_table;
+        Some(self.symbol_num - 1)
     }
 
-    fn dump(&self) -> Vec<u8> {
-        let mut total_size = 9usize;
-        for i in 0..self.len_histo.len() {
-            total_size += self.len_histo[i] as usize * (i + 1);
-        }
-        let mut buf = Vec::with_capacity(total_size);
-        buf.push(Endian::get_native_endian().into());
-        self.len_histo.iter().for_each(|l| buf.push(*l));
-        for i in 0..self.symbol_num {
-            let s = self.get_symbol(i);
-            let mut num = s.as_u64();
-            for _ in 0..s.length() {
-                buf.push(num as u8);
-                num >>= 8;
-            }
+    fn dump_with_endian_into(&self, endian: Endian, out: &mut Vec<u8>) {
+        let symbols = (0..self.symbol_num).map(|i| self.get_symbol(i));
+        let flags = if self.length_ordered { 0 } else { TABLE_FLAG_UNSORTED };
+        dump_symbols(flags, endian, self.escape, &self.length_histogram(), symbols, out)
+    }
+
+    fn dump_len(&self) -> usize {
+        let symbol_lens = if self.length_ordered { 0 } else { self.len() };
+        dump_len(false, self.escape, &self.length_histogram()) + symbol_lens
+    }
+
+    fn escape_code(&self) -> u8 {
+        self.escape
+    }
+
+    fn iter_symbols(&self) -> Box<dyn Iterator<Item = (u16, Vec<u8>)> + '_> {
+        Box::new(self.learned_symbols().iter().map(|s| (s.code(), s.bytes())))
+    }
+
+    fn prune_unused(&mut self, corpus: &[&[u8]]) -> usize {
+        let used = used_symbols(self, corpus);
+        let removed = self.len() - used.len();
+        let mut symbol_table = PerfectHashSymbolTable::new();
+        symbol_table.set_escape_code(self.escape);
+        for s in used {
+            symbol_table.add(s);
         }
-        buf
+        symbol_table.finalize_codes(self.length_ordered);
+        *self = symbol_table;
+        removed
+    }
+
+    fn length_histogram(&self) -> [usize; Symbol::MAX_LEN] {
+        self.len_histo.map(|l| l as usize)
     }
 }
 
 impl Display for PerfectHashSymbolTable {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let (start, end) = if self.finalized {
-            (0usize, self.symbol_num as usize)
-        } else {
-            (CODE_BASE as usize, (CODE_BASE + self.symbol_num) as usize)
-        };
-        let symbols_str = &self.symbols[start..end].iter()
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        let symbols_str = &self.learned_symbols().iter()
             .map(|&x| x.to_string())
             .collect::<Vec<String>>()
             .join(", ");
@@ -259,134 +519,1033 @@ impl Display for PerfectHashSymbolTable {
     }
 }
 
-pub struct SymbolTableBuilder {
-    counter: Counter,
-    count_frac: u32,
+/// symbol table using two-byte codes
+/// codes below CODE_BASE stand for the single bytes, so no escape is needed, and the learned
+/// symbols (at least two bytes long) take the codes from CODE_BASE up to CODE_MAX_WIDE
+#[derive(Clone)]
+struct WideSymbolTable {
+    // lookup table using the next two bytes, or just the next single byte
+    short_codes: Vec<u16>,
+    hash_table: Vec<Symbol>,
+    symbols: Vec<Symbol>,
+    len_histo: [u16; Symbol::MAX_LEN],
+    symbol_num: u16,
 }
 
-impl SymbolTableBuilder {
-    pub fn build_from(s: &str) -> Box<dyn SymbolTable> {
-        let str = String::from(s);
-        let sample = vec![&str];
-        SymbolTableBuilder {
-            counter: Counter::new(),
-            count_frac: 0,
-        }.build(&sample)
+impl WideSymbolTable {
+    const TABLE_SIZE: usize = 1 << 15;
+    const MAX_SYMBOLS: usize = (CODE_MAX_WIDE - CODE_BASE) as usize;
+
+    pub fn new() -> WideSymbolTable {
+        let mut symbols = vec![Symbol::from_byte_code(0, CODE_MASK_WIDE); CODE_MAX_WIDE as usize];
+        for i in 0..CODE_BASE {
+            symbols[i as usize] = Symbol::from_byte_code(i as u8, i);
+        }
+        let short_codes = (0..=u16::MAX).map(|i| (1 << LEN_BITS) | (i & 0xff)).collect();
+        WideSymbolTable {
+            short_codes,
+            hash_table: vec![Symbol::free(); Self::TABLE_SIZE],
+            symbols,
+            len_histo: [0u16; Symbol::MAX_LEN],
+            symbol_num: 0,
+        }
     }
 
-    pub fn build_from_samples(samples: &Vec<&String>) -> Box<dyn SymbolTable> {
-        SymbolTableBuilder {
-            counter: Counter::new(),
-            count_frac: 5,
-        }.build(samples)
+    fn hash_idx(hash_value: usize) -> usize {
+        hash_value & (Self::TABLE_SIZE - 1)
     }
 
-    fn build(&mut self, samples: &Vec<&String>) -> Box<dyn SymbolTable> {
-        let mut symbol_table: Box<dyn SymbolTable> = Box::new(PerfectHashSymbolTable::new());
-        let mut best_table = symbol_table.clone_box();
-        let mut best_gain = i64::MIN;
-        let mut best_single = [0u8; Counter::ENTRY_SIZE * 2];
-        let mut sample_frac = 8;
-        loop {
-            let gain = self.compute_freq(samples, sample_frac, &symbol_table);
-            if gain > best_gain {
-                best_gain = gain;
-                best_single = self.counter.backup_single();
-                best_table = symbol_table.clone_box();
-            }
-            if sample_frac >= 128 {
-                break;
-            }
-            self.make_table(sample_frac, &mut symbol_table);
-            self.counter.reset();
-            sample_frac += 30;
-        }
-        self.counter.restore_single(best_single);
-        self.make_table(sample_frac, &mut best_table);
-        best_table.finalize();
-        best_table
+    fn learned_symbols(&self) -> &[Symbol] {
+        &self.symbols[CODE_BASE as usize..(CODE_BASE + self.symbol_num) as usize]
     }
+}
 
-    fn compute_freq(&mut self, samples: &Vec<&String>, sample_frac: u32, symbol_table: &Box<dyn SymbolTable>) -> i64 {
-        let mut gain = 0i64;
-        for i in 0..samples.len() {
-            if samples.len() > 128 && sample_frac < 128 {
-                let rand = 1 + ((fsst_hash(1 + i) * sample_frac as usize) & 127);
-                if rand > sample_frac as usize {
-                    continue;
-                }
+impl SymbolTable for WideSymbolTable {
+    fn add(&mut self, mut s: Symbol) -> bool {
+        let len = s.length();
+        // single bytes already have their own codes
+        if len < 2 || self.len() >= Self::MAX_SYMBOLS {
+            return false;
+        }
+
+        let code = CODE_BASE + self.symbol_num;
+        s.set_code_len(code, len);
+        if len == 2 {
+            self.short_codes[s.first2()] = code | (2 << LEN_BITS);
+        } else {
+            let src_symbol = &mut self.hash_table[Self::hash_idx(s.hash())];
+            if src_symbol.taken() {
+                return false;
             }
-            gain += self.count_line(samples[i].as_bytes(), sample_frac, symbol_table);
+            src_symbol.update_to(&s);
         }
-        gain
+
+        self.symbols[code as usize] = s;
+        self.symbol_num += 1;
+        self.len_histo[len - 1] += 1;
+        true
     }
 
-    fn count_line(&mut self, str_bytes: &[u8], sample_frac: u32, symbol_table: &Box<dyn SymbolTable>) -> i64 {
-        let mut gain = 0i64;
-        let mut pos = 0;
-        let mut code1 = symbol_table.find_longest_symbol_code(&str_bytes);
-        let mut s1 = symbol_table.get_symbol(code1);
-        loop {
-            self.counter.inc_single(code1 as usize);
-            if s1.length() > 1 {
-                self.counter.inc_single(str_bytes[pos] as usize);
-            }
-            gain += s1.length() as i64 - (1 + is_escape_code(code1) as i64);
-            pos += s1.length();
-            if pos >= str_bytes.len() {
-                break;
-            }
+    fn find_longest_symbol_code(&self, str_bytes: &[u8]) -> u16 {
+        let target_symbol = Symbol::from_bytes(str_bytes);
+        let src_symbol = &self.hash_table[Self::hash_idx(target_symbol.hash())];
+        if target_symbol.prefix_match(src_symbol) {
+            return src_symbol.code();
+        }
 
-            let code2 = symbol_table.find_longest_symbol_code(&str_bytes[pos..]);
-            let s2 = symbol_table.get_symbol(code2);
-            if sample_frac < 128 {
-                self.counter.inc_concat(code1 as usize, code2 as usize);
-                if s2.length() > 1 {
-                    self.counter.inc_concat(code1 as usize, str_bytes[pos] as usize);
-                }
+        if target_symbol.length() >= 2 {
+            let code = self.short_codes[target_symbol.first2()] & CODE_MASK_WIDE;
+            if code >= CODE_BASE {
+                return code;
             }
-            code1 = code2;
-            s1 = s2;
         }
-        gain
+
+        target_symbol.first() as u16
     }
 
-    fn make_table(&mut self, sample_frac: u32, symbol_table: &mut Box<dyn SymbolTable>) {
-        let mut candidates: HashMap<Symbol, u32> = HashMap::with_capacity(CODE_MAX as usize);
-        let end = CODE_BASE as usize + symbol_table.len();
-        let mut pos1 = 0usize;
-        while pos1 < end {
-            let cnt1 = self.counter.get_single_and_forward(&mut pos1);
-            if cnt1 == 0 {
-                pos1 += 1;
-                continue;
-            }
+    fn get_symbol(&self, code: u16) -> &Symbol {
+        &self.symbols[code as usize]
+    }
 
-            let s1 = symbol_table.get_symbol(pos1 as u16);
-            let heuristic_cnt = match s1.length() {
-                1 => 8 * cnt1,
-                _ => cnt1
-            };
-            self.expand_candidate(&mut candidates, s1.clone(), heuristic_cnt, sample_frac);
-            if s1.length() == Symbol::MAX_LEN
-                || sample_frac >= 128 {
-                pos1 += 1;
-                continue;
-            }
+    fn encode_for(&self, target: &Symbol) -> (u16, usize, usize) {
+        let src_symbol = &self.hash_table[Self::hash_idx(target.hash())];
+        if target.prefix_match(src_symbol) {
+            return (src_symbol.code(), src_symbol.length(), 2);
+        }
+        if target.length() < 2 {
+            return (target.first() as u16, 1, 2);
+        }
+
+        let code = self.short_codes[target.first2()];
+        (code & CODE_MASK_WIDE, (code >> LEN_BITS) as usize, 2)
+    }
+
+    fn len(&self) -> usize {
+        self.symbol_num as usize
+    }
+
+    fn clear(&mut self) {
+        for i in CODE_BASE..CODE_BASE + self.symbol_num {
+            let s = self.symbols[i as usize];
+            if s.length() == 2 {
+                let v = s.first2();
+                self.short_codes[v] = (v as u16 & 0xff) | (1 << LEN_BITS);
+            } else {
+                self.hash_table[Self::hash_idx(s.hash())].reset();
+            }
+        }
+        self.len_histo.fill(0);
+        self.symbol_num = 0;
+    }
+
+    fn finalize(&mut self) {
+        // renumber the symbols ordered by length, which is the order they are dumped in
+        let mut symbols = self.learned_symbols().to_vec();
+        symbols.sort_by_key(|s| s.length());
+        self.clear();
+        for s in symbols {
+            self.add(s);
+        }
+    }
+
+    /// the dump of a wide table is ordered by length, so it only takes a symbol of 2 or more bytes
+    /// that is at least as long as its longest symbol, which keeps the order without renumbering
+    fn try_add_symbol(&mut self, bytes: &[u8]) -> Option<u16> {
+        if bytes.len() < 2 || bytes.len() > Symbol::MAX_LEN {
+            return None;
+        }
+        if let Some((code, _)) = self.iter_symbols().find(|(_, symbol)| symbol.as_slice() == bytes) {
+            return Some(code);
+        }
+        let longest = self.learned_symbols().iter().map(|s| s.length()).max().unwrap_or(0);
+        if bytes.len() < longest || !self.add(Symbol::from_bytes(bytes)) {
+            return None;
+        }
+        Some(CODE_BASE + self.symbol_num - 1)
+    }
+
+    fn dump_with_endian_into(&self, endian: Endian, out: &mut Vec<u8>) {
+        let symbols = self.learned_symbols();
+        dump_symbols(TABLE_FLAG_WIDE, endian, CODE_ESCAPE, &self.length_histogram(), symbols.iter(), out)
+    }
+
+    fn is_wide(&self) -> bool {
+        true
+    }
+
+    fn iter_symbols(&self) -> Box<dyn Iterator<Item = (u16, Vec<u8>)> + '_> {
+        Box::new(self.learned_symbols().iter().map(|s| (s.code(), s.bytes())))
+    }
+
+    fn prune_unused(&mut self, corpus: &[&[u8]]) -> usize {
+        let used = used_symbols(self, corpus);
+        let removed = self.len() - used.len();
+        let mut symbol_table = WideSymbolTable::new();
+        for s in used {
+            symbol_table.add(s);
+        }
+        symbol_table.finalize();
+        *self = symbol_table;
+        removed
+    }
+
+    fn length_histogram(&self) -> [usize; Symbol::MAX_LEN] {
+        self.len_histo.map(|l| l as usize)
+    }
+}
+
+impl Display for WideSymbolTable {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        let symbols_str = &self.learned_symbols().iter()
+            .map(|&x| x.to_string())
+            .collect::<Vec<String>>()
+            .join(", ");
+        write!(f, "[{}]", symbols_str)
+    }
+}
+
+/// symbol table without symbols, the input is stored verbatim with every byte being its own code
+/// it is used as a fallback for data that does not compress
+#[derive(Clone)]
+struct IdentitySymbolTable {
+    symbols: Vec<Symbol>,
+}
+
+impl IdentitySymbolTable {
+    pub fn new() -> IdentitySymbolTable {
+        IdentitySymbolTable {
+            symbols: (0..CODE_BASE).map(|i| Symbol::from_byte_code(i as u8, i)).collect(),
+        }
+    }
+}
+
+impl SymbolTable for IdentitySymbolTable {
+    fn add(&mut self, _: Symbol) -> bool {
+        false
+    }
+
+    fn find_longest_symbol_code(&self, str_bytes: &[u8]) -> u16 {
+        str_bytes[0] as u16
+    }
+
+    fn get_symbol(&self, code: u16) -> &Symbol {
+        &self.symbols[code as usize]
+    }
+
+    fn encode_for(&self, target: &Symbol) -> (u16, usize, usize) {
+        (target.first() as u16, 1, 1)
+    }
+
+    fn len(&self) -> usize {
+        0
+    }
+
+    fn clear(&mut self) {}
+
+    fn finalize(&mut self) {}
+
+    fn dump_with_endian_into(&self, endian: Endian, out: &mut Vec<u8>) {
+        dump_symbols(TABLE_FLAG_IDENTITY, endian, CODE_ESCAPE, &self.length_histogram(), core::iter::empty(), out)
+    }
+
+    fn is_identity(&self) -> bool {
+        true
+    }
+
+    fn iter_symbols(&self) -> Box<dyn Iterator<Item = (u16, Vec<u8>)> + '_> {
+        Box::new(core::iter::empty())
+    }
+
+    fn prune_unused(&mut self, _corpus: &[&[u8]]) -> usize {
+        0
+    }
+
+    fn length_histogram(&self) -> [usize; Symbol::MAX_LEN] {
+        [0; Symbol::MAX_LEN]
+    }
+}
+
+impl Display for IdentitySymbolTable {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "[]")
+    }
+}
+
+/// the learned symbols that are emitted at least once when encoding the corpus, in code order
+fn used_symbols<T: SymbolTable + ?Sized>(table: &T, corpus: &[&[u8]]) -> Vec<Symbol> {
+    let counts = Encoder::from_table_ref(table).usage_counts(corpus);
+    table
+        .iter_symbols()
+        .filter(|(code, _)| counts[*code as usize] > 0)
+        .map(|(_, bytes)| Symbol::from_bytes(&bytes))
+        .collect()
+}
+
+/// move the values into a boxed array without building the array on the stack first, which
+/// `Box::new([..])` may do
+fn boxed_array<T, const N: usize>(values: Vec<T>) -> Box<[T; N]> {
+    match values.into_boxed_slice().try_into() {
+        Ok(array) => array,
+        Err(values) => panic!("expected {} values, got {}", N, values.len()),
+    }
+}
+
+/// length of a dump with the given length histogram
+fn dump_len(wide: bool, escape: u8, len_histo: &[usize; Symbol::MAX_LEN]) -> usize {
+    let histo_entry_size = if wide { 2 } else { 1 };
+    let mut total_size = TABLE_HEADER_SIZE + Symbol::MAX_LEN * histo_entry_size;
+    if escape != CODE_ESCAPE {
+        total_size += 1;
+    }
+    for (i, l) in len_histo.iter().enumerate() {
+        total_size += l * (i + 1);
+    }
+    total_size
+}
+
+/// append the dump of the symbols in code order, which must be sorted by length unless the
+/// `TABLE_FLAG_UNSORTED` flag is set
+/// the layout is | magic | version | flags | endian | (escape) | length histogram | symbols |
+/// where the escape code is only present if it is not 255, the histogram entries are two bytes
+/// (little endian) for wide tables and one byte otherwise, and the bytes of every symbol are in
+/// string order for little endian and reversed for big endian, preceded by a length byte for
+/// unsorted tables
+/// a compact histogram starts with a byte marking the lengths having symbols and only holds their
+/// entries
+fn dump_symbols<'a>(
+    mut flags: u8,
+    endian: Endian,
+    escape: u8,
+    len_histo: &[usize; Symbol::MAX_LEN],
+    symbols: impl Iterator<Item = &'a Symbol>,
+    buf: &mut Vec<u8>,
+) {
+    let wide = flags & TABLE_FLAG_WIDE != 0;
+    if escape != CODE_ESCAPE {
+        flags |= TABLE_FLAG_ESCAPE;
+    }
+    let unsorted = flags & TABLE_FLAG_UNSORTED != 0;
+    let compact = flags & TABLE_FLAG_COMPACT != 0;
+    let symbol_lens = if unsorted { len_histo.iter().sum::<usize>() } else { 0 };
+    buf.reserve(dump_len(wide, escape, len_histo) + symbol_lens);
+    buf.extend_from_slice(&TABLE_MAGIC);
+    buf.push(TABLE_FORMAT_VERSION);
+    buf.push(flags);
+    buf.push(endian.into());
+    if escape != CODE_ESCAPE {
+        buf.push(escape);
+    }
+    if compact {
+        let present = len_histo.iter().enumerate().filter(|(_, l)| **l > 0).fold(0u8, |mask, (i, _)| mask | 1 << i);
+        buf.push(present);
+    }
+    for l in len_histo.iter().filter(|l| !compact || **l > 0) {
+        if wide {
+            buf.extend_from_slice(&(*l as u16).to_le_bytes());
+        } else {
+            buf.push(*l as u8);
+        }
+    }
+    for s in symbols {
+        let mut bytes = s.bytes();
+        if endian == Endian::Big {
+            bytes.reverse();
+        }
+        if unsorted {
+            buf.push(bytes.len() as u8);
+        }
+        buf.extend_from_slice(&bytes);
+    }
+}
+
+/// a parsed symbol table dump
+pub(crate) struct DumpedTable {
+    /// end position of the table in the buffer
+    pub end_pos: usize,
+    pub wide: bool,
+    pub identity: bool,
+    pub escape: u8,
+    /// whether the codes are ordered by symbol length, see `PerfectHashSymbolTable::finalize_in_order`
+    pub length_ordered: bool,
+    /// symbols in code order
+    pub symbols: Vec<Symbol>,
+}
+
+/// parse a dumped symbol table
+pub(crate) fn load_symbols(buf: &[u8]) -> Result<DumpedTable, DecodeError> {
+    if buf.len() < TABLE_MAGIC.len() + 1 {
+        return Err(DecodeError::TooShort);
+    }
+    if buf[..TABLE_MAGIC.len()] != TABLE_MAGIC {
+        return Err(DecodeError::BadMagic);
+    }
+    let mut pos = TABLE_MAGIC.len();
+    let version = buf[pos];
+    pos += 1;
+    let flags = match version {
+        // version 1 has no flags byte
+        1 => 0,
+        TABLE_FORMAT_VERSION => {
+            let flags = *buf.get(pos).ok_or(DecodeError::TooShort)?;
+            pos += 1;
+            flags
+        }
+        _ => return Err(DecodeError::UnsupportedVersion(version)),
+    };
+    if flags & !TABLE_FLAGS != 0 {
+        return Err(DecodeError::UnknownFlags(flags));
+    }
+    let wide = flags & TABLE_FLAG_WIDE != 0;
+    let identity = flags & TABLE_FLAG_IDENTITY != 0;
+    let encode_endian = Endian::from_u8(*buf.get(pos).ok_or(DecodeError::TooShort)?)?;
+    pos += 1;
+    let escape = if flags & TABLE_FLAG_ESCAPE != 0 {
+        pos += 1;
+        *buf.get(pos - 1).ok_or(DecodeError::TooShort)?
+    } else {
+        CODE_ESCAPE
+    };
+
+    let mut len_histo = [0usize; Symbol::MAX_LEN];
+    let histo_entry_size = if wide { 2 } else { 1 };
+    let present = if flags & TABLE_FLAG_COMPACT != 0 {
+        pos += 1;
+        *buf.get(pos - 1).ok_or(DecodeError::TooShort)?
+    } else {
+        u8::MAX
+    };
+    let lens = (0..Symbol::MAX_LEN).filter(|i| present & (1 << i) != 0).collect::<Vec<usize>>();
+    let histo_bytes = buf.get(pos..pos + lens.len() * histo_entry_size).ok_or(DecodeError::TooShort)?;
+    for (i, l) in lens.into_iter().zip(histo_bytes.chunks(histo_entry_size)) {
+        len_histo[i] = if wide { u16::from_le_bytes([l[0], l[1]]) as usize } else { l[0] as usize };
+    }
+    pos += histo_bytes.len();
+    let symbol_num = len_histo.iter().sum::<usize>();
+    if symbol_num > SymbolTableBuilder::code_capacity(wide) {
+        return Err(DecodeError::TooManySymbols(symbol_num));
+    }
+
+    let length_ordered = flags & TABLE_FLAG_UNSORTED == 0;
+    let mut symbols = Vec::with_capacity(symbol_num);
+    if length_ordered {
+        for len in 1..=Symbol::MAX_LEN {
+            for _ in 0..len_histo[len - 1] {
+                symbols.push(load_symbol(buf, &mut pos, len, encode_endian)?);
+            }
+        }
+    } else {
+        let mut unsorted_histo = [0usize; Symbol::MAX_LEN];
+        for _ in 0..symbol_num {
+            let len = *buf.get(pos).ok_or(DecodeError::TooShort)? as usize;
+            pos += 1;
+            if len == 0 || len > Symbol::MAX_LEN {
+                return Err(DecodeError::InvalidSymbolTable);
+            }
+            unsorted_histo[len - 1] += 1;
+            symbols.push(load_symbol(buf, &mut pos, len, encode_endian)?);
+        }
+        if unsorted_histo != len_histo {
+            return Err(DecodeError::InvalidSymbolTable);
+        }
+    }
+    let escaped = escape != CODE_ESCAPE;
+    if identity && (wide || escaped || !symbols.is_empty()) || wide && (escaped || !length_ordered) {
+        return Err(DecodeError::InvalidSymbolTable);
+    }
+    Ok(DumpedTable { end_pos: pos, wide, identity, escape, length_ordered, symbols })
+}
+
+/// read a dumped symbol of `len` bytes at `pos` and advance `pos` past it
+fn load_symbol(buf: &[u8], pos: &mut usize, len: usize, encode_endian: Endian) -> Result<Symbol, DecodeError> {
+    let mut bytes = buf.get(*pos..*pos + len).ok_or(DecodeError::TooShort)?.to_vec();
+    if encode_endian == Endian::Big {
+        bytes.reverse();
+    }
+    *pos += len;
+    Ok(Symbol::from_bytes(&bytes))
+}
+
+/// what the builder optimizes the table for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OptimizeFor {
+    /// the best compression ratio
+    Ratio,
+    /// fewer escapes, which keeps decoding on its fast path, at some cost of ratio
+    /// single bytes seen in the samples take codes before any longer symbol
+    DecodeSpeed,
+}
+
+/// the counters of the training take hundreds of KB, they are only allocated by the first training
+/// and reset by the next ones, so a builder kept for training many tables (one per block, say)
+/// allocates them once
+pub struct SymbolTableBuilder {
+    counter: Option<Counter>,
+    count_frac: u32,
+    wide: bool,
+    max_symbols: Option<u16>,
+    seed: u64,
+    optimize_for: OptimizeFor,
+    escape: u8,
+    reservoir_sampling: bool,
+    low_memory: bool,
+    sample_target: usize,
+    order_by_frequency: bool,
+    hints: Vec<Symbol>,
+    record_marker: bool,
+    // the sample fractions of the training rounds, in 128ths, see `training_schedule`
+    sample_frac_start: u32,
+    sample_frac_step: u32,
+    sample_frac_cap: u32,
+}
+
+impl Default for SymbolTableBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SymbolTableBuilder {
+    pub fn new() -> SymbolTableBuilder {
+        SymbolTableBuilder {
+            counter: None,
+            count_frac: 5,
+            wide: false,
+            max_symbols: None,
+            seed: DEFAULT_SAMPLE_SEED,
+            optimize_for: OptimizeFor::Ratio,
+            escape: CODE_ESCAPE,
+            reservoir_sampling: false,
+            low_memory: false,
+            sample_target: SAMPLE_TARGET,
+            order_by_frequency: false,
+            hints: Vec::new(),
+            record_marker: false,
+            sample_frac_start: 8,
+            sample_frac_step: 30,
+            sample_frac_cap: 128,
+        }
+    }
+
+    /// build tables with two-byte codes, which hold up to 3840 symbols instead of 255 but take two
+    /// output bytes for every code
+    pub fn wide_codes(mut self, wide: bool) -> SymbolTableBuilder {
+        if wide != self.wide {
+            self.counter = None;
+            self.wide = wide;
+        }
+        self
+    }
+
+    /// count the pairs of symbols in a sparse map instead of dense counters, which otherwise take
+    /// 384 KB (24 MB with two-byte codes), so the working set stays small for small samples
+    /// the training is slower and the counts are exact instead of 12 bits, which can change the
+    /// learned table slightly
+    pub fn low_memory(mut self, low_memory: bool) -> SymbolTableBuilder {
+        if low_memory != self.low_memory {
+            self.counter = None;
+            self.low_memory = low_memory;
+        }
+        self
+    }
+
+    /// stop adding symbols once the table holds `max_symbols` of them, which makes the dumped table
+    /// smaller at the cost of more escapes
+    /// defaults to 255, the most a table with one-byte codes can hold (3840 with two-byte codes)
+    pub fn max_symbols(mut self, max_symbols: u16) -> SymbolTableBuilder {
+        self.max_symbols = Some(max_symbols);
+        self
+    }
+
+    /// leave the code after the learned symbols free for `Encoder::encode_with_marker`, which ends
+    /// every record with it so `Decoder::decode_records` can find the record boundaries again
+    /// the table holds one symbol less, and adding symbols with `try_add_symbol` takes the free code
+    pub fn record_marker(mut self, record_marker: bool) -> SymbolTableBuilder {
+        self.record_marker = record_marker;
+        self
+    }
+
+    /// seed of the sampling in `train_by_sampling`, the same strings and seed always build the same
+    /// table, defaults to `DEFAULT_SAMPLE_SEED`
+    pub fn seed(mut self, seed: u64) -> SymbolTableBuilder {
+        self.seed = seed;
+        self
+    }
+
+    /// defaults to `OptimizeFor::Ratio`, tables with two-byte codes have no escapes so they ignore
+    /// `OptimizeFor::DecodeSpeed`
+    pub fn optimize_for(mut self, optimize_for: OptimizeFor) -> SymbolTableBuilder {
+        self.optimize_for = optimize_for;
+        self
+    }
+
+    /// use another escape code than 255 for tables with one-byte codes, see
+    /// `PerfectHashSymbolTable::set_escape_code`
+    pub fn escape_code(mut self, escape: u8) -> SymbolTableBuilder {
+        self.escape = escape;
+        self
+    }
+
+    /// sample whole records with `take_sample_reservoir` in `train_by_sampling`, which gives every
+    /// record the same chance regardless of its position and learns better tables on sorted data
+    pub fn reservoir_sampling(mut self, reservoir_sampling: bool) -> SymbolTableBuilder {
+        self.reservoir_sampling = reservoir_sampling;
+        self
+    }
+
+    /// the size in bytes `train_by_sampling` and `train_by_sampling_bytes` sample, defaults to 64 KB
+    /// a larger sample can learn a better table for a large corpus but takes longer to train on
+    pub fn sample_target_bytes(mut self, sample_target: usize) -> SymbolTableBuilder {
+        self.sample_target = sample_target;
+        self
+    }
+
+    /// give the symbols used most often on the samples the lowest codes, so the decoder mostly
+    /// reads the start of its symbol array, see `PerfectHashSymbolTable::finalize_in_order`
+    /// the dump stores the length of every symbol then, tables with two-byte codes ignore it
+    pub fn order_by_frequency(mut self, order_by_frequency: bool) -> SymbolTableBuilder {
+        self.order_by_frequency = order_by_frequency;
+        self
+    }
+
+    /// substrings of 1 to 8 bytes known to be frequent, such as URL prefixes or JSON keys, which
+    /// are in the table the training starts from, so they are scored like learned symbols and only
+    /// the ones that pay off are kept
+    /// longer or empty hints are ignored
+    pub fn hints(mut self, hints: &[&[u8]]) -> SymbolTableBuilder {
+        self.hints.clear();
+        for hint in hints {
+            if !hint.is_empty() && hint.len() <= Symbol::MAX_LEN && !self.hints.iter().any(|s| s.bytes() == *hint) {
+                self.hints.push(Symbol::from_bytes(hint));
+            }
+        }
+        self
+    }
+
+    /// the rounds of the training, which learns a table on a growing fraction of the samples, in
+    /// 128ths, starting at `start` and growing by `step` until it reaches `cap` in the last round
+    /// defaults to 8, 30 and 128, which is five rounds, fewer rounds train faster and more rounds
+    /// can learn a slightly better table, a single round only learns symbols of one byte
+    /// the cap is at most 128, the whole samples, and the step at least 1
+    pub fn training_schedule(mut self, start: u32, step: u32, cap: u32) -> SymbolTableBuilder {
+        self.sample_frac_start = start;
+        self.sample_frac_step = step.max(1);
+        self.sample_frac_cap = cap.min(128);
+        self
+    }
+
+    /// build a symbol table from samples, starting the training from a table of the hints, see `hints`
+    pub fn build_with_hints(samples: &[&str], hints: &[&[u8]]) -> Box<dyn SymbolTable> {
+        Self::new().hints(hints).train_from_samples(samples)
+    }
+
+    pub fn build_from(s: &str) -> Box<dyn SymbolTable> {
+        Self::new().train_from(s)
+    }
+
+    pub fn build_from_samples<S: AsRef<str>>(samples: &[S]) -> Box<dyn SymbolTable> {
+        Self::new().train_from_samples(samples)
+    }
+
+    /// build a symbol table for raw bytes, which do not need to be valid UTF-8
+    pub fn build_from_bytes(bytes: &[u8]) -> Box<dyn SymbolTable> {
+        Self::new().train_from_bytes(bytes)
+    }
+
+    /// build a symbol table for one large buffer from segments sampled across it, see
+    /// `take_sample_from_bytes`
+    pub fn build_by_sampling_bytes(bytes: &[u8]) -> Box<dyn SymbolTable> {
+        Self::new().train_by_sampling_bytes(bytes)
+    }
+
+    /// build a symbol table for a single string with the options of this builder
+    pub fn train_from(&mut self, s: &str) -> Box<dyn SymbolTable> {
+        self.train_from_bytes(s.as_bytes())
+    }
+
+    /// build a symbol table for raw bytes with the options of this builder
+    pub fn train_from_bytes(&mut self, bytes: &[u8]) -> Box<dyn SymbolTable> {
+        self.count_frac = 0;
+        self.build(&[bytes])
+    }
+
+    /// build a symbol table from samples with the options of this builder
+    pub fn train_from_samples<S: AsRef<str>>(&mut self, samples: &[S]) -> Box<dyn SymbolTable> {
+        self.count_frac = 5;
+        let samples = samples.iter().map(|s| s.as_ref().as_bytes()).collect::<Vec<&[u8]>>();
+        self.build(&samples)
+    }
+
+    /// sample the strings with the seed of this builder and build a symbol table from the sample
+    pub fn train_by_sampling<S: AsRef<str>>(&mut self, strings: &[S]) -> Box<dyn SymbolTable> {
+        let sample = self.take_sample(strings, self.sample_target);
+        self.train_from_samples(&sample)
+    }
+
+    /// sample every column with a share of the sample size proportional to the column size and build
+    /// one table from the merged samples, so columns with overlapping vocabulary can share it
+    pub fn train_shared_by_sampling<S: AsRef<str>>(&mut self, columns: &[&[S]]) -> Box<dyn SymbolTable> {
+        let sizes = columns
+            .iter()
+            .map(|column| column.iter().map(|s| s.as_ref().len()).sum::<usize>())
+            .collect::<Vec<usize>>();
+        let total_size = sizes.iter().sum::<usize>().max(1);
+        let mut sample = Vec::new();
+        for (column, size) in columns.iter().zip(sizes) {
+            let target = (self.sample_target as u128 * size as u128 / total_size as u128) as usize;
+            sample.extend(self.take_sample(column, target));
+        }
+        self.train_from_samples(&sample)
+    }
+
+    /// sample about `target` bytes of the strings with the sampling and seed of this builder
+    fn take_sample<'a, S: AsRef<str>>(&self, strings: &'a [S], target: usize) -> Vec<&'a S> {
+        if self.reservoir_sampling {
+            take_sample_reservoir(strings, target, self.seed)
+        } else {
+            take_sample_with_target(strings, self.seed, target)
+        }
+    }
+
+    /// sample segments spread over the buffer and build a symbol table from them with the options of
+    /// this builder, a buffer shorter than 16 KB is used whole
+    pub fn train_by_sampling_bytes(&mut self, bytes: &[u8]) -> Box<dyn SymbolTable> {
+        let sample = take_sample_from_bytes_with_target(bytes, self.sample_target);
+        self.train_from_bytes(&sample)
+    }
+
+    /// how many bytes the symbol saves on the samples when it is forced into the table this builder
+    /// starts training from, counted by one pass of the gain accounting of the training with and
+    /// without the symbol, no table is trained
+    /// returns 0 for a symbol the table can not hold
+    pub fn candidate_gain(&self, symbol: &Symbol, samples: &[&str]) -> i64 {
+        let symbol_table = self.hinted_table();
+        let mut candidate_table = symbol_table.clone_box();
+        if symbol.length() == 0 || candidate_table.len() >= self.symbol_limit() || !candidate_table.add(*symbol) {
+            return 0;
+        }
+        self.samples_gain(samples, &candidate_table) - self.samples_gain(samples, &symbol_table)
+    }
+
+    /// the gain `count_line` counts for the table on every sample, the counts themselves are dropped
+    #[allow(clippy::borrowed_box)]
+    fn samples_gain(&self, samples: &[&str], symbol_table: &Box<dyn SymbolTable>) -> i64 {
+        let mut counter = Self::new_counter(self.wide, true);
+        samples.iter().map(|s| Self::count_line(&mut counter, self.wide, s.as_bytes(), 128, symbol_table)).sum()
+    }
+
+    /// predict the compression factor a table with one-byte codes reaches on the samples from one
+    /// counting pass of the training over a table without symbols, instead of the rounds of training,
+    /// so data that clearly does not compress can skip training
+    /// the pass counts the bytes and the pairs of adjacent bytes, every byte given a code saves its
+    /// escape and every occurrence of a pair given a code saves a byte, but adjacent pairs overlap so
+    /// only half of the pair counts are taken
+    /// symbols longer than two bytes are not seen, so the prediction stays below 2.0 and below the
+    /// factor of a trained table on text, it is close to the trained factor on random bytes and an
+    /// empty sample gives 1.0
+    pub fn estimate_table_quality(&self, samples: &[&str]) -> f64 {
+        let sample = self.take_sample(samples, self.sample_target);
+        let trivial_table = Self::new_table(false, self.escape);
+        // the sparse counter is small for one pass and counts the frequent pairs without saturating
+        let mut counter = Self::new_counter(false, true);
+        let (mut input_len, mut gain) = (0i64, 0i64);
+        for s in sample {
+            input_len += s.len() as i64;
+            // pairs are only counted below a sample fraction of 128
+            gain += Self::count_line(&mut counter, false, s.as_bytes(), 0, &trivial_table);
+        }
+        if input_len == 0 {
+            return 1.0;
+        }
+        // the savings are doubled so half a pair count stays an integer
+        let mut savings = Vec::new();
+        let mut pos1 = 0usize;
+        while pos1 < CODE_BASE as usize {
+            let cnt1 = counter.get_single_and_forward(&mut pos1);
+            if cnt1 == 0 || pos1 >= CODE_BASE as usize {
+                pos1 += 1;
+                continue;
+            }
+            savings.push(2 * cnt1 as i64);
+            let mut pos2 = 0usize;
+            while pos2 < CODE_BASE as usize {
+                let cnt2 = counter.get_concat_and_forward(pos1, &mut pos2);
+                if cnt2 > 0 && pos2 < CODE_BASE as usize {
+                    savings.push(cnt2 as i64);
+                }
+                pos2 += 1;
+            }
+            pos1 += 1;
+        }
+        savings.sort_unstable_by_key(|&saving| Reverse(saving));
+        let max_symbols = min(self.symbol_limit(), Self::code_capacity(false));
+        let saved = savings.iter().take(max_symbols).sum::<i64>() / 2;
+        // the trivial table escapes every byte, the gain is minus the input length
+        input_len as f64 / (input_len - gain - saved) as f64
+    }
+
+    /// build a table from substring counts gathered elsewhere, such as a previous pass over the
+    /// data, instead of sampling and counting, the counts are selected like the counts of the last
+    /// training round, with the escape code, wide codes, `max_symbols`, `count_frac` and
+    /// `optimize_for` of this builder
+    /// substrings that are empty or longer than `Symbol::MAX_LEN` can not be symbols and are ignored
+    #[cfg(feature = "std")]
+    pub fn build_from_frequencies(&self, freqs: &HashMap<Vec<u8>, u32>) -> Box<dyn SymbolTable> {
+        let mut candidates: BTreeMap<Symbol, u32> = BTreeMap::new();
+        for (bytes, &cnt) in freqs {
+            if bytes.is_empty() || bytes.len() > Symbol::MAX_LEN {
+                continue;
+            }
+            // single bytes are favoured like in `make_table`, every one without a code is escaped
+            let heuristic_cnt = if bytes.len() == 1 { cnt.saturating_mul(8) } else { cnt };
+            self.expand_candidate(&mut candidates, Symbol::from_bytes(bytes), heuristic_cnt, 128);
+        }
+        let mut symbol_table = Self::new_table(self.wide, self.escape);
+        self.add_best_candidates(&candidates, &mut symbol_table);
+        symbol_table.finalize();
+        symbol_table
+    }
+
+    /// rebuild a symbol table from the bytes dumped by `SymbolTable::dump`
+    /// the returned table supports encoding just like the table that was dumped
+    pub fn load_from_bytes(buf: &[u8]) -> Result<Box<dyn SymbolTable>, DecodeError> {
+        Self::build_from_dumped(load_symbols(buf)?)
+    }
+
+    /// a table without symbols that stores the input verbatim, for data that does not compress
+    pub fn identity_table() -> Box<dyn SymbolTable> {
+        Box::new(IdentitySymbolTable::new())
+    }
+
+    /// build a finalized symbol table from a parsed dump
+    pub(crate) fn build_from_dumped(dumped: DumpedTable) -> Result<Box<dyn SymbolTable>, DecodeError> {
+        if dumped.identity {
+            return Ok(Self::identity_table());
+        }
+        let (symbols, wide) = (dumped.symbols, dumped.wide);
+        if !dumped.length_ordered {
+            let mut symbol_table = PerfectHashSymbolTable::new();
+            symbol_table.set_escape_code(dumped.escape);
+            Self::add_dumped(&mut symbol_table, symbols, CODE_ESCAPE as usize)?;
+            symbol_table.finalize_in_order();
+            return Ok(Box::new(symbol_table));
+        }
+        let mut symbol_table = Self::new_table(wide, dumped.escape);
+        Self::add_dumped(symbol_table.as_mut(), symbols, Self::code_capacity(wide))?;
+        symbol_table.finalize();
+        Ok(symbol_table)
+    }
+
+    fn add_dumped(symbol_table: &mut dyn SymbolTable, symbols: Vec<Symbol>, max_symbols: usize) -> Result<(), DecodeError> {
+        for s in symbols {
+            if symbol_table.len() >= max_symbols || !symbol_table.add(s) {
+                return Err(DecodeError::InvalidSymbolTable);
+            }
+        }
+        Ok(())
+    }
+
+    /// the counters of this builder, allocated on first use
+    fn counter(&mut self) -> &mut Counter {
+        let (wide, low_memory) = (self.wide, self.low_memory);
+        self.counter.get_or_insert_with(|| Self::new_counter(wide, low_memory))
+    }
+
+    fn new_counter(wide: bool, low_memory: bool) -> Counter {
+        let entry_size = if wide { CODE_MAX_WIDE } else { CODE_MAX };
+        if low_memory {
+            Counter::new_sparse(entry_size as usize)
+        } else {
+            Counter::new(entry_size as usize)
+        }
+    }
+
+    fn new_table(wide: bool, escape: u8) -> Box<dyn SymbolTable> {
+        if wide {
+            return Box::new(WideSymbolTable::new());
+        }
+        let mut symbol_table = Box::new(PerfectHashSymbolTable::new());
+        symbol_table.set_escape_code(escape);
+        symbol_table
+    }
+
+    /// the most symbols a table can hold
+    fn code_capacity(wide: bool) -> usize {
+        if wide {
+            WideSymbolTable::MAX_SYMBOLS
+        } else {
+            CODE_ESCAPE as usize
+        }
+    }
+
+    /// the most symbols a built table holds, `max_symbols` up to the codes left by the record marker
+    fn symbol_limit(&self) -> usize {
+        let capacity = Self::code_capacity(self.wide) - self.record_marker as usize;
+        self.max_symbols.map_or(capacity, |n| min(n as usize, capacity))
+    }
+
+    /// an empty table holding the hints, which training starts from
+    fn hinted_table(&self) -> Box<dyn SymbolTable> {
+        let mut symbol_table = Self::new_table(self.wide, self.escape);
+        let max_symbols = self.symbol_limit();
+        for s in &self.hints {
+            if symbol_table.len() >= max_symbols {
+                break;
+            }
+            symbol_table.add(*s);
+        }
+        symbol_table
+    }
+
+    fn build(&mut self, samples: &[&[u8]]) -> Box<dyn SymbolTable> {
+        let mut symbol_table = self.hinted_table();
+        let mut best_table = symbol_table.clone_box();
+        let mut best_gain = i64::MIN;
+        let mut best_single = Vec::new();
+        let mut sample_frac = self.sample_frac_start;
+        self.counter().reset();
+        loop {
+            let gain = self.compute_freq(samples, sample_frac, &symbol_table);
+            if gain > best_gain {
+                best_gain = gain;
+                best_single = self.counter().backup_single();
+                best_table = symbol_table.clone_box();
+            }
+            if sample_frac >= self.sample_frac_cap {
+                break;
+            }
+            self.make_table(sample_frac, &mut symbol_table);
+            self.counter().reset();
+            sample_frac += self.sample_frac_step;
+        }
+        self.counter().restore_single(&best_single);
+        // only the single counts are restored, so the last table is made without pairs
+        self.make_table(128, &mut best_table);
+        best_table.finalize();
+        if self.order_by_frequency && !self.wide {
+            return self.reorder_by_frequency(best_table.as_ref(), samples);
+        }
+        best_table
+    }
+
+    /// rebuild the finalized table with the codes ordered by how often they are emitted on the
+    /// samples, symbols emitted equally often keep their order
+    fn reorder_by_frequency(&self, symbol_table: &dyn SymbolTable, samples: &[&[u8]]) -> Box<dyn SymbolTable> {
+        let counts = Encoder::from_table_ref(symbol_table).usage_counts(samples);
+        let mut symbols = symbol_table
+            .iter_symbols()
+            .map(|(code, bytes)| (counts[code as usize], Symbol::from_bytes(&bytes)))
+            .collect::<Vec<(u64, Symbol)>>();
+        symbols.sort_by_key(|(count, _)| Reverse(*count));
+        let mut ordered_table = PerfectHashSymbolTable::new();
+        ordered_table.set_escape_code(self.escape);
+        for (_, s) in symbols {
+            ordered_table.add(s);
+        }
+        ordered_table.finalize_in_order();
+        Box::new(ordered_table)
+    }
+
+    #[allow(clippy::borrowed_box)]
+    fn compute_freq(&mut self, samples: &[&[u8]], sample_frac: u32, symbol_table: &Box<dyn SymbolTable>) -> i64 {
+        let mut gain = 0i64;
+        for i in 0..samples.len() {
+            if samples.len() > 128 && sample_frac < 128 {
+                let rand = 1 + ((fsst_hash(1 + i) * sample_frac as usize) & 127);
+                if rand > sample_frac as usize {
+                    continue;
+                }
+            }
+            let wide = self.wide;
+            gain += Self::count_line(self.counter(), wide, samples[i], sample_frac, symbol_table);
+        }
+        gain
+    }
+
+    /// count the codes of the line and the pairs of codes, unless `sample_frac` is 128, and return the
+    /// gain of the table over escaping every byte
+    #[allow(clippy::borrowed_box)]
+    fn count_line(
+        counter: &mut Counter,
+        wide: bool,
+        str_bytes: &[u8],
+        sample_frac: u32,
+        symbol_table: &Box<dyn SymbolTable>,
+    ) -> i64 {
+        if str_bytes.is_empty() {
+            return 0;
+        }
+        let mut gain = 0i64;
+        let mut pos = 0;
+        let mut code1 = symbol_table.find_longest_symbol_code(str_bytes);
+        let mut s1 = symbol_table.get_symbol(code1);
+        loop {
+            counter.inc_single(code1 as usize);
+            if s1.length() > 1 {
+                counter.inc_single(str_bytes[pos] as usize);
+            }
+            let code_len = if wide { 2 } else { 1 + is_escape_code(code1) as i64 };
+            gain += s1.length() as i64 - code_len;
+            pos += s1.length();
+            if pos >= str_bytes.len() {
+                break;
+            }
+
+            let code2 = symbol_table.find_longest_symbol_code(&str_bytes[pos..]);
+            let s2 = symbol_table.get_symbol(code2);
+            if sample_frac < 128 {
+                counter.inc_concat(code1 as usize, code2 as usize);
+                if s2.length() > 1 {
+                    counter.inc_concat(code1 as usize, str_bytes[pos] as usize);
+                }
+            }
+            code1 = code2;
+            s1 = s2;
+        }
+        gain
+    }
+
+    fn make_table(&mut self, sample_frac: u32, symbol_table: &mut Box<dyn SymbolTable>) {
+        let mut candidates: BTreeMap<Symbol, u32> = BTreeMap::new();
+        let end = CODE_BASE as usize + symbol_table.len();
+        let mut pos1 = 0usize;
+        while pos1 < end {
+            let cnt1 = self.counter().get_single_and_forward(&mut pos1);
+            if cnt1 == 0 {
+                pos1 += 1;
+                continue;
+            }
+
+            let s1 = symbol_table.get_symbol(pos1 as u16);
+            let heuristic_cnt = match s1.length() {
+                1 => 8 * cnt1,
+                _ => cnt1
+            };
+            self.expand_candidate(&mut candidates, *s1, heuristic_cnt, sample_frac);
+            if s1.length() == Symbol::MAX_LEN
+                || sample_frac >= 128 {
+                pos1 += 1;
+                continue;
+            }
 
             let mut pos2 = 0usize;
             while pos2 < end {
-                let cnt2 = self.counter.get_concat_and_forward(pos1, &mut pos2);
+                let cnt2 = self.counter().get_concat_and_forward(pos1, &mut pos2);
                 if cnt2 > 0 {
                     let s2 = symbol_table.get_symbol(pos2 as u16);
-                    let s3 = *s1 + *s2;
-                    self.expand_candidate(&mut candidates, s3, cnt2, sample_frac);
+                    // a concatenation longer than a symbol is not a candidate
+                    if let Some(s3) = s1.checked_add(*s2) {
+                        self.expand_candidate(&mut candidates, s3, cnt2, sample_frac);
+                    }
                 }
                 pos2 += 1;
             }
             pos1 += 1;
         }
+        self.add_best_candidates(&candidates, symbol_table);
+    }
 
+    /// clear the table and fill it with the candidates of the highest gain, up to `max_symbols`
+    fn add_best_candidates(&self, candidates: &BTreeMap<Symbol, u32>, symbol_table: &mut Box<dyn SymbolTable>) {
         let mut sorted_vec: Vec<(Symbol, u32)> = candidates.iter().map(|(k, v)| (*k, *v)).collect();
         sorted_vec.sort_by(|a, b| {
             if a.1 == b.1 {
@@ -395,17 +1554,681 @@ impl SymbolTableBuilder {
                 a.1.cmp(&b.1)
             }
         });
+        if self.optimize_for == OptimizeFor::DecodeSpeed {
+            // move the single bytes to the end so they are popped first, every one of them that
+            // gets a code is a byte that is never escaped
+            sorted_vec.sort_by_key(|(s, _)| s.length() == 1);
+        }
         symbol_table.clear();
-        while symbol_table.len() < 255 && !sorted_vec.is_empty() {
+        let max_symbols = self.symbol_limit();
+        while symbol_table.len() < max_symbols && !sorted_vec.is_empty() {
             let s = sorted_vec.pop().unwrap();
             symbol_table.add(s.0);
         }
     }
 
-    fn expand_candidate(&self, candidates: &mut HashMap<Symbol, u32>, s: Symbol, cnt: u32, sample_frac: u32) {
+    fn expand_candidate(&self, candidates: &mut BTreeMap<Symbol, u32>, s: Symbol, cnt: u32, sample_frac: u32) {
         if cnt >= (self.count_frac * sample_frac / 128) {
             let gain = s.length() as u32 * cnt;
             candidates.insert(s, candidates.get(&s).unwrap_or(&0) + gain);
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{encode_all_strings, read_string_lines};
+    use crate::core::codec::Encoder;
+    use crate::core::error::DecodeError;
+    use crate::core::symbol::Symbol;
+    use crate::core::symbol_table::{OptimizeFor, PerfectHashSymbolTable, SymbolTable, SymbolTableBuilder};
+    use crate::core::{CODE_ESCAPE, DEFAULT_SAMPLE_SEED, TABLE_HEADER_SIZE, xorshift};
+    use crate::Endian;
+
+    #[test]
+    pub fn test_load_from_bytes() {
+        let test_str = "paqvawflxucgajxfzxwooypirnzkahobfvxzhrerdwzkerwwolqfbafwslwhsvuitbtgkvnjrdr";
+        let symbol_table = SymbolTableBuilder::build_from(test_str);
+        let loaded_table = SymbolTableBuilder::load_from_bytes(&symbol_table.dump()).unwrap();
+        assert_eq!(symbol_table.len(), loaded_table.len());
+        assert_eq!(symbol_table.to_string(), loaded_table.to_string());
+        assert_eq!(symbol_table.dump(), loaded_table.dump());
+        let encoding = Encoder::from_table(&symbol_table).encode_str(test_str);
+        assert_eq!(encoding, Encoder::from_table(&loaded_table).encode_str(test_str));
+
+        let strings = read_string_lines("assets/test_data/l_comment").unwrap();
+        let (symbol_table, encodings) = encode_all_strings(&strings);
+        let loaded_table = SymbolTableBuilder::load_from_bytes(&symbol_table.dump()).unwrap();
+        let encoder = Encoder::from_table(&loaded_table);
+        for i in 0..strings.len() {
+            assert_eq!(encodings[i], encoder.encode_str(&strings[i]));
+        }
+    }
+
+    #[test]
+    pub fn test_iter_symbols() {
+        let test_str = "tumcwitumvldb";
+        let symbol_table = SymbolTableBuilder::build_from(test_str);
+        let symbols = symbol_table.iter_symbols().collect::<Vec<(u16, Vec<u8>)>>();
+        assert_eq!(symbol_table.len(), symbols.len());
+        for (i, (code, bytes)) in symbols.iter().enumerate() {
+            assert_eq!(i as u16, *code);
+            assert_eq!(symbol_table.get_symbol(*code).to_string().as_bytes(), bytes.as_slice());
+        }
+        assert!(symbols.windows(2).all(|w| w[0].1.len() <= w[1].1.len()));
+    }
+
+    #[test]
+    pub fn test_to_dictionary() {
+        let test_str = "tumcwitumvldb";
+        let mut builder = SymbolTableBuilder::new().escape_code(1);
+        for symbol_table in [builder.train_from(test_str), SymbolTableBuilder::new().wide_codes(true).train_from(test_str)] {
+            let dictionary = symbol_table.to_dictionary();
+            assert_eq!(symbol_table.len(), dictionary.len());
+            // every symbol encodes to its code alone
+            let encoder = Encoder::from_table(&symbol_table);
+            for (bytes, code) in &dictionary {
+                let encoding = encoder.encode_bytes(bytes);
+                let written = if symbol_table.is_wide() {
+                    u16::from_le_bytes([encoding[0], encoding[1]])
+                } else {
+                    assert_eq!(1, encoding.len());
+                    encoding[0] as u16
+                };
+                assert_eq!(*code, written);
+            }
+        }
+        assert!(SymbolTableBuilder::identity_table().to_dictionary().is_empty());
+    }
+
+    #[test]
+    pub fn test_length_histogram() {
+        let strings = read_string_lines("assets/test_data/l_comment").unwrap();
+        let (symbol_table, _) = encode_all_strings(&strings);
+        let len_histo = symbol_table.length_histogram();
+        assert_eq!(symbol_table.len(), len_histo.iter().sum::<usize>());
+        for (i, n) in len_histo.iter().enumerate() {
+            let count = symbol_table.iter_symbols().filter(|(_, bytes)| bytes.len() == i + 1).count();
+            assert_eq!(count, *n);
+        }
+    }
+
+    #[test]
+    pub fn test_symbols_by_length() {
+        let strings = read_string_lines("assets/test_data/l_comment").unwrap();
+        let (symbol_table, _) = encode_all_strings(&strings);
+        let buckets = symbol_table.symbols_by_length();
+        let len_histo = symbol_table.length_histogram();
+        for (i, bucket) in buckets.iter().enumerate() {
+            assert_eq!(len_histo[i], bucket.len());
+            assert!(bucket.iter().all(|bytes| bytes.len() == i + 1));
+        }
+        let symbols = symbol_table.iter_symbols().map(|(_, bytes)| bytes).collect::<Vec<Vec<u8>>>();
+        assert_eq!(symbols, buckets.concat());
+        assert!(SymbolTableBuilder::identity_table().symbols_by_length().iter().all(|bucket| bucket.is_empty()));
+    }
+
+    #[test]
+    pub fn test_train_by_sampling_bytes() {
+        let data = std::fs::read("assets/test_data/l_comment").unwrap();
+        let symbol_table = SymbolTableBuilder::build_by_sampling_bytes(&data);
+        assert!(!symbol_table.is_empty());
+        let encoding = Encoder::from_table(&symbol_table).encode_bytes(&data);
+        assert!(encoding.len() < data.len());
+        let decoder = crate::core::codec::Decoder::from_table(&symbol_table);
+        assert_eq!(data, decoder.decode_bytes(&encoding));
+        // short buffers are not sampled
+        let short = &data[..1000];
+        let sampled_table = SymbolTableBuilder::build_by_sampling_bytes(short);
+        assert_eq!(SymbolTableBuilder::build_from_bytes(short).dump(), sampled_table.dump());
+    }
+
+    #[test]
+    pub fn test_reservoir_sampling() {
+        let strings = read_string_lines("assets/test_data/l_comment").unwrap();
+        let symbol_table = SymbolTableBuilder::new().reservoir_sampling(true).train_by_sampling(&strings);
+        let same_table = SymbolTableBuilder::new().reservoir_sampling(true).train_by_sampling(&strings);
+        assert_eq!(symbol_table.dump(), same_table.dump());
+        let decoder = crate::core::codec::Decoder::from_table(&symbol_table);
+        let encoder = Encoder::from_table(&symbol_table);
+        for str in strings.iter().rev().take(100) {
+            assert_eq!(*str, decoder.decode(&encoder.encode_str(str)));
+        }
+    }
+
+    #[test]
+    pub fn test_sample_target_bytes() {
+        let strings = read_string_lines("assets/test_data/l_comment").unwrap();
+        let symbol_table = SymbolTableBuilder::new().train_by_sampling(&strings);
+        let same_table = SymbolTableBuilder::new().sample_target_bytes(1 << 16).train_by_sampling(&strings);
+        assert_eq!(symbol_table.dump(), same_table.dump());
+        let small_table = SymbolTableBuilder::new().sample_target_bytes(1 << 12).train_by_sampling(&strings);
+        assert_ne!(symbol_table.dump(), small_table.dump());
+        let decoder = crate::core::codec::Decoder::from_table(&small_table);
+        let encoder = Encoder::from_table(&small_table);
+        for str in strings.iter().take(100) {
+            assert_eq!(*str, decoder.decode(&encoder.encode_str(str)));
+        }
+
+        let data = std::fs::read("assets/test_data/l_comment").unwrap();
+        let symbol_table = SymbolTableBuilder::build_by_sampling_bytes(&data);
+        let large_table = SymbolTableBuilder::new().sample_target_bytes(1 << 18).train_by_sampling_bytes(&data);
+        assert_ne!(symbol_table.dump(), large_table.dump());
+    }
+
+    #[test]
+    pub fn test_candidate_gain() {
+        let samples = ["tumcwitumvldb", "tumcwitumvldb", "witumvldb"];
+        let builder = SymbolTableBuilder::new().max_symbols(1);
+        // every "vldb" takes one byte instead of four escaped bytes taking two each
+        assert_eq!(3 * 7, builder.candidate_gain(&Symbol::from_str("vldb"), &samples));
+        assert_eq!(0, builder.candidate_gain(&Symbol::from_str("xyz"), &samples));
+        assert_eq!(0, builder.candidate_gain(&Symbol::from_str(""), &samples));
+        // the hint takes the only code
+        let builder = SymbolTableBuilder::new().max_symbols(1).hints(&[b"tumc"]);
+        assert_eq!(0, builder.candidate_gain(&Symbol::from_str("vldb"), &samples));
+        // the gain is measured against the table training starts from, where the hint "tumc" takes
+        // the "c" every "cwitu" starts with
+        assert_eq!(2 * 9, SymbolTableBuilder::new().candidate_gain(&Symbol::from_str("cwitu"), &samples));
+        let builder = SymbolTableBuilder::new().hints(&[b"tumc"]);
+        assert_eq!(0, builder.candidate_gain(&Symbol::from_str("cwitu"), &samples));
+
+        let strings = read_string_lines("assets/test_data/l_comment").unwrap();
+        let sample = crate::core::take_sample(&strings).into_iter().map(|s| s.as_str()).collect::<Vec<&str>>();
+        let builder = SymbolTableBuilder::new();
+        let trained_table = SymbolTableBuilder::build_from_samples(&sample);
+        let (_, learned) = trained_table.iter_symbols().find(|(_, bytes)| bytes.len() > 2).unwrap();
+        assert!(builder.candidate_gain(&Symbol::from_bytes(&learned), &sample) > 0);
+        assert_eq!(0, builder.candidate_gain(&Symbol::from_bytes(b"\x01\x02\x03"), &sample));
+    }
+
+    #[test]
+    pub fn test_estimate_table_quality() {
+        let builder = SymbolTableBuilder::new();
+        assert_eq!(1.0, builder.estimate_table_quality(&[]));
+        assert_eq!(1.0, builder.estimate_table_quality(&["", ""]));
+        // the 9 escapes are saved and half of the 8 pairs
+        assert_eq!(9.0 / 5.0, builder.estimate_table_quality(&["aaaaaaaaa"]));
+
+        let mut rand = DEFAULT_SAMPLE_SEED;
+        let random = (0..2000)
+            .map(|_| (0..40).map(|_| {
+                rand = xorshift(rand);
+                (rand % 128) as u8 as char
+            }).collect::<String>())
+            .collect::<Vec<String>>();
+        let assets = ["l_comment", "ps_comment", "c_name"]
+            .map(|name| read_string_lines(format!("assets/test_data/{}", name)).unwrap());
+        for (strings, is_random) in assets.iter().map(|strings| (strings, false)).chain([(&random, true)]) {
+            let strings = strings.iter().map(|s| s.as_str()).collect::<Vec<&str>>();
+            let estimate = builder.estimate_table_quality(&strings);
+            let trained_factor = SymbolTableBuilder::build_from_samples(&strings)
+                .estimate_compression_factor(strings.concat().as_bytes());
+            if is_random {
+                assert!((estimate - trained_factor).abs() < 0.1);
+            } else {
+                // pairs alone find more than half of the compression of the trained symbols on text
+                assert!(estimate > 1.5 && estimate < trained_factor && estimate > trained_factor / 2.0);
+            }
+            assert_eq!(estimate, SymbolTableBuilder::new().wide_codes(true).estimate_table_quality(&strings));
+            assert_eq!(estimate, SymbolTableBuilder::new().low_memory(true).estimate_table_quality(&strings));
+        }
+    }
+
+    #[test]
+    pub fn test_order_by_frequency() {
+        let strings = read_string_lines("assets/test_data/l_comment").unwrap();
+        let sample = crate::core::take_sample(&strings);
+        let symbol_table = SymbolTableBuilder::build_from_samples(&sample);
+        let ordered_table = SymbolTableBuilder::new().order_by_frequency(true).train_from_samples(&sample);
+        let mut symbols = symbol_table.iter_symbols().map(|(_, bytes)| bytes).collect::<Vec<Vec<u8>>>();
+        let mut ordered_symbols = ordered_table.iter_symbols().map(|(_, bytes)| bytes).collect::<Vec<Vec<u8>>>();
+        assert_ne!(symbols, ordered_symbols);
+        symbols.sort();
+        ordered_symbols.sort();
+        assert_eq!(symbols, ordered_symbols);
+
+        let sample_bytes = sample.iter().map(|s| s.as_bytes()).collect::<Vec<&[u8]>>();
+        let counts = Encoder::from_table(&ordered_table).usage_counts(&sample_bytes);
+        assert!(counts[..ordered_table.len()].windows(2).all(|w| w[0] >= w[1]));
+
+        // the dump keeps the order, so the loaded table and the decoder use the same codes
+        let dump = ordered_table.dump();
+        assert_eq!(dump.len(), ordered_table.dump_len());
+        assert_eq!(symbol_table.dump_len() + ordered_table.len(), ordered_table.dump_len());
+        let loaded_table = SymbolTableBuilder::load_from_bytes(&dump).unwrap();
+        assert_eq!(dump, loaded_table.dump());
+        let encoder = Encoder::from_table(&ordered_table);
+        let (_, decoder) = crate::core::codec::Decoder::from_table_bytes(&dump).unwrap();
+        for str in strings.iter().take(1000) {
+            let encoding = encoder.encode_str(str);
+            assert_eq!(encoding, Encoder::from_table(&loaded_table).encode_str(str));
+            assert_eq!(*str, decoder.decode(&encoding));
+        }
+        for endian in [Endian::Little, Endian::Big] {
+            let loaded_table = SymbolTableBuilder::load_from_bytes(&ordered_table.dump_with_endian(endian)).unwrap();
+            assert_eq!(ordered_table.to_string(), loaded_table.to_string());
+        }
+        let mut bad_len = dump.clone();
+        bad_len[TABLE_HEADER_SIZE + Symbol::MAX_LEN] = 9;
+        assert_eq!(DecodeError::InvalidSymbolTable, SymbolTableBuilder::load_from_bytes(&bad_len).err().unwrap());
+
+        // pruning keeps the order of the symbols left
+        let counts = Encoder::from_table(&ordered_table).usage_counts(&sample_bytes[..10]);
+        let used_symbols = ordered_table.iter_symbols().filter(|(code, _)| counts[*code as usize] > 0).map(|(_, bytes)| bytes);
+        let mut pruned_table = ordered_table.clone_box();
+        pruned_table.prune_unused(&sample_bytes[..10]);
+        assert!(pruned_table.iter_symbols().map(|(_, bytes)| bytes).eq(used_symbols));
+
+        let wide_table = SymbolTableBuilder::new().wide_codes(true).order_by_frequency(true).train_from_samples(&sample);
+        assert_eq!(SymbolTableBuilder::new().wide_codes(true).train_from_samples(&sample).dump(), wide_table.dump());
+    }
+
+    #[test]
+    pub fn test_build_with_hints() {
+        let strings = (0..2000)
+            .map(|i| format!("https://example.com/{}/{}", ["a", "bb", "ccc"][i % 3], i))
+            .collect::<Vec<String>>();
+        let samples = strings.iter().map(|s| s.as_str()).collect::<Vec<&str>>();
+        let hints: [&[u8]; 3] = [b"https://", b"\x01\x02\x03", b"example.com/ccc/too_long"];
+        let symbol_table = SymbolTableBuilder::build_with_hints(&samples, &hints);
+        let symbols = symbol_table.iter_symbols().map(|(_, bytes)| bytes).collect::<Vec<Vec<u8>>>();
+        // the hint that pays off survives the scoring, the one never seen is dropped
+        assert!(symbols.contains(&b"https://".to_vec()));
+        assert!(!symbols.contains(&b"\x01\x02\x03".to_vec()));
+        let encoder = Encoder::from_table(&symbol_table);
+        let decoder = crate::core::codec::Decoder::from_table(&symbol_table);
+        for str in &strings {
+            assert_eq!(*str, decoder.decode(&encoder.encode_str(str)));
+        }
+
+        assert_eq!(
+            SymbolTableBuilder::build_from_samples(&samples).dump(),
+            SymbolTableBuilder::build_with_hints(&samples, &[]).dump()
+        );
+        let wide_table = SymbolTableBuilder::new().wide_codes(true).hints(&hints).train_from_samples(&samples);
+        assert!(wide_table.iter_symbols().all(|(_, bytes)| bytes != b"\x01\x02\x03"));
+    }
+
+    #[test]
+    pub fn test_low_memory() {
+        let strings = read_string_lines("assets/test_data/l_comment").unwrap();
+        let sample = crate::core::take_sample(&strings);
+        let input_bytes = strings.iter().map(|s| s.len()).sum::<usize>();
+        let encoded_bytes = |symbol_table: &dyn SymbolTable| {
+            let encoder = Encoder::from_table_ref(symbol_table);
+            strings.iter().map(|s| encoder.encoded_len(s.as_bytes())).sum::<usize>()
+        };
+        let full_table = SymbolTableBuilder::build_from_samples(&sample);
+        for wide in [false, true] {
+            let symbol_table = SymbolTableBuilder::new().wide_codes(wide).low_memory(true).train_from_samples(&sample);
+            let decoder = crate::core::codec::Decoder::from_table(&symbol_table);
+            let encoder = Encoder::from_table(&symbol_table);
+            for str in strings.iter().take(100) {
+                assert_eq!(*str, decoder.decode(&encoder.encode_str(str)));
+            }
+            assert!(encoded_bytes(symbol_table.as_ref()) < input_bytes);
+        }
+        let symbol_table = SymbolTableBuilder::new().low_memory(true).train_from_samples(&sample);
+        assert!((encoded_bytes(symbol_table.as_ref()) as f64) < encoded_bytes(full_table.as_ref()) as f64 * 1.1);
+    }
+
+    #[test]
+    pub fn test_build_from_frequencies() {
+        let strings = read_string_lines("assets/test_data/l_comment").unwrap();
+        let strings = &strings[..300];
+        let mut freqs = std::collections::HashMap::new();
+        for str in strings {
+            let bytes = str.as_bytes();
+            for start in 0..bytes.len() {
+                for end in start + 1..=(start + Symbol::MAX_LEN).min(bytes.len()) {
+                    *freqs.entry(bytes[start..end].to_vec()).or_insert(0u32) += 1;
+                }
+            }
+        }
+        freqs.insert(Vec::new(), 1000);
+        freqs.insert(b"too long to be a symbol".to_vec(), 1000);
+        let symbol_table = SymbolTableBuilder::new().build_from_frequencies(&freqs);
+        assert_eq!(symbol_table.dump(), SymbolTableBuilder::new().build_from_frequencies(&freqs).dump());
+        assert!(symbol_table.iter_symbols().all(|(_, symbol)| !symbol.is_empty() && symbol.len() <= Symbol::MAX_LEN));
+        let encoder = Encoder::from_table(&symbol_table);
+        let decoder = crate::core::codec::Decoder::from_table(&symbol_table);
+        let input = strings.concat();
+        let encoding = encoder.encode_str(&input);
+        assert!(encoding.len() < input.len());
+        assert_eq!(input, decoder.decode(&encoding));
+
+        let symbol_table = SymbolTableBuilder::new().max_symbols(10).build_from_frequencies(&freqs);
+        assert_eq!(10, symbol_table.len());
+        let symbol_table = SymbolTableBuilder::new().wide_codes(true).build_from_frequencies(&freqs);
+        let encoding = Encoder::from_table(&symbol_table).encode_str(&input);
+        assert_eq!(input, crate::core::codec::Decoder::from_table(&symbol_table).decode(&encoding));
+        assert_eq!(0, SymbolTableBuilder::new().build_from_frequencies(&Default::default()).len());
+    }
+
+    #[test]
+    pub fn test_training_schedule() {
+        let strings = read_string_lines("assets/test_data/l_comment").unwrap();
+        let sample = crate::core::take_sample(&strings);
+        let encoded_bytes = |symbol_table: &dyn SymbolTable| {
+            let encoder = Encoder::from_table_ref(symbol_table);
+            strings.iter().take(2000).map(|s| encoder.encoded_len(s.as_bytes())).sum::<usize>()
+        };
+        let input_bytes = strings.iter().take(2000).map(|s| s.len()).sum::<usize>();
+        let default_table = SymbolTableBuilder::build_from_samples(&sample);
+        for (start, step, cap) in [(8, 30, 128), (8, 30, 500)] {
+            let symbol_table = SymbolTableBuilder::new().training_schedule(start, step, cap).train_from_samples(&sample);
+            assert_eq!(default_table.dump(), symbol_table.dump());
+        }
+        // a single round only learns symbols of one byte
+        for (start, step, cap, rounds) in [(128, 1, 128, 1), (8, 0, 8, 1), (8, 60, 68, 2), (4, 10, 128, 14)] {
+            let symbol_table = SymbolTableBuilder::new().training_schedule(start, step, cap).train_from_samples(&sample);
+            let decoder = crate::core::codec::Decoder::from_table(&symbol_table);
+            let encoder = Encoder::from_table(&symbol_table);
+            for str in strings.iter().take(100) {
+                assert_eq!(*str, decoder.decode(&encoder.encode_str(str)));
+            }
+            assert_eq!(rounds > 1, encoded_bytes(symbol_table.as_ref()) * 3 < input_bytes * 2);
+        }
+    }
+
+    #[test]
+    pub fn test_reuse_builder() {
+        let strings = read_string_lines("assets/test_data/c_name").unwrap();
+        let mut builder = SymbolTableBuilder::new().wide_codes(true).wide_codes(false);
+        assert!(builder.counter.is_none());
+        for block in strings.chunks(500).take(8) {
+            let symbol_table = builder.train_from_samples(block);
+            assert_eq!(SymbolTableBuilder::build_from_samples(block).dump(), symbol_table.dump());
+            assert!(builder.counter.is_some());
+        }
+        let mut builder = builder.low_memory(true);
+        assert!(builder.counter.is_none());
+        let symbol_table = builder.train_from_samples(&strings[..500]);
+        assert_eq!(SymbolTableBuilder::new().low_memory(true).train_from_samples(&strings[..500]).dump(), symbol_table.dump());
+    }
+
+    #[test]
+    pub fn test_build_on_small_stack() {
+        let handle = std::thread::Builder::new()
+            .stack_size(128 << 10)
+            .spawn(|| {
+                let mut tables = Vec::new();
+                for wide in [false, true] {
+                    let mut builder = SymbolTableBuilder::new().wide_codes(wide);
+                    let symbol_table = builder.train_from("tumcwitumvldb tumcwitumvldb tumcwitumvldb");
+                    tables.push(symbol_table.clone_box());
+                    tables.push(symbol_table);
+                }
+                tables.iter().all(|t| !t.is_empty())
+            })
+            .unwrap();
+        assert!(handle.join().unwrap());
+    }
+
+    #[test]
+    pub fn test_max_symbols() {
+        let strings = read_string_lines("assets/test_data/l_comment").unwrap();
+        let sample = crate::core::take_sample(&strings);
+        let full_table = SymbolTableBuilder::build_from_samples(&sample);
+        let symbol_table = SymbolTableBuilder::new().max_symbols(32).train_from_samples(&sample);
+        assert_eq!(32, symbol_table.len());
+        assert!(symbol_table.dump().len() < full_table.dump().len());
+        let decoder = crate::core::codec::Decoder::from_table(&symbol_table);
+        let encoder = Encoder::from_table(&symbol_table);
+        for str in strings.iter().take(100) {
+            assert_eq!(*str, decoder.decode(&encoder.encode_str(str)));
+        }
+
+        let symbol_table = SymbolTableBuilder::new().max_symbols(0).train_from_samples(&sample);
+        assert!(symbol_table.is_empty());
+        let symbol_table = SymbolTableBuilder::new().max_symbols(1000).train_from_samples(&sample);
+        assert_eq!(full_table.dump(), symbol_table.dump());
+    }
+
+    #[test]
+    pub fn test_hand_built_table() {
+        let mut symbol_table = PerfectHashSymbolTable::new();
+        assert!(symbol_table.add_bytes(b"lineitem"));
+        assert!(symbol_table.add_bytes(b"or"));
+        assert!(symbol_table.add_bytes(b"d"));
+        assert!(!symbol_table.add_bytes(b""));
+        assert!(!symbol_table.add_bytes(b"too long symbol"));
+        symbol_table.finalize();
+        assert!(!symbol_table.add_bytes(b"ers"));
+        assert_eq!(3, symbol_table.len());
+
+        let test_str = "orders lineitem";
+        let encoder = Encoder::from_table_ref(&symbol_table);
+        let encoding = encoder.encode_str(test_str);
+        // "or" "d" "e" "r" "s" " " "lineitem", where "e", "r", "s" and " " are escaped
+        assert_eq!(11, encoding.len());
+        let decoder = crate::core::codec::Decoder::from_table_ref(&symbol_table);
+        assert_eq!(test_str, decoder.decode(&encoding));
+    }
+
+    #[test]
+    pub fn test_table_eq() {
+        let build = |symbols: &[&[u8]]| {
+            let mut symbol_table = PerfectHashSymbolTable::new();
+            for s in symbols {
+                assert!(symbol_table.add_bytes(s));
+            }
+            symbol_table.finalize();
+            symbol_table
+        };
+        let symbol_table = build(&[b"lineitem", b"or", b"d", b"li"]);
+        assert!(symbol_table == build(&[b"lineitem", b"or", b"d", b"li"]));
+        assert!(symbol_table != build(&[b"lineitem", b"or", b"d"]));
+        // the same symbols added in another order get other codes
+        assert!(symbol_table != build(&[b"lineitem", b"li", b"d", b"or"]));
+        let mut escaped_table = symbol_table.clone();
+        escaped_table.set_escape_code(0);
+        assert!(symbol_table != escaped_table);
+
+        // rebuilding the table resets its lookup tables but keeps the symbols and codes
+        let mut rebuilt_table = symbol_table.clone();
+        assert_eq!(0, rebuilt_table.prune_unused(&[b"orders lineitem lid"]));
+        assert!(symbol_table == rebuilt_table);
+    }
+
+    #[test]
+    pub fn test_dump_with_endian() {
+        let test_str = "paqvawflxucgajxfzxwooypirnzkahobfvxzhrerdwzkerwwolqfbafwslwhsvuitbtgkvnjrdr";
+        let symbol_table = SymbolTableBuilder::build_from(test_str);
+        let little = symbol_table.dump_with_endian(Endian::Little);
+        let big = symbol_table.dump_with_endian(Endian::Big);
+        assert_eq!(symbol_table.dump(), symbol_table.dump_with_endian(Endian::get_native_endian()));
+        assert_ne!(little, big);
+        assert_eq!(little.len(), big.len());
+        let encoding = Encoder::from_table(&symbol_table).encode_str(test_str);
+        for buf in [little, big] {
+            let loaded_table = SymbolTableBuilder::load_from_bytes(&buf).unwrap();
+            assert_eq!(symbol_table.to_string(), loaded_table.to_string());
+            let (_, decoder) = crate::core::codec::Decoder::from_table_bytes(&buf).unwrap();
+            assert_eq!(test_str, decoder.decode(&encoding));
+        }
+    }
+
+    #[test]
+    pub fn test_dump_into() {
+        let symbol_table = SymbolTableBuilder::build_from("tumcwitumvldb");
+        let wide_table = SymbolTableBuilder::new().wide_codes(true).train_from("tumcwitumvldb");
+        let identity_table = SymbolTableBuilder::identity_table();
+        let mut buf = b"header".to_vec();
+        for table in [&symbol_table, &wide_table, &identity_table] {
+            table.dump_into(&mut buf);
+        }
+        let expected = [b"header".to_vec(), symbol_table.dump(), wide_table.dump(), identity_table.dump()].concat();
+        assert_eq!(expected, buf);
+    }
+
+    #[test]
+    pub fn test_dump_compact() {
+        let test_str = "tumcwitumvldb";
+        let mut unsorted_table = PerfectHashSymbolTable::new();
+        unsorted_table.add_bytes(b"tumc");
+        unsorted_table.add_bytes(b"wi");
+        unsorted_table.finalize_in_order();
+        let tables = [
+            SymbolTableBuilder::build_from(test_str),
+            SymbolTableBuilder::new().escape_code(0).train_from(test_str),
+            SymbolTableBuilder::new().wide_codes(true).train_from(test_str),
+            SymbolTableBuilder::identity_table(),
+            Box::new(unsorted_table),
+        ];
+        for symbol_table in &tables {
+            let dump = symbol_table.dump_compact();
+            let present = symbol_table.length_histogram().iter().filter(|l| **l > 0).count();
+            let entry_size = if symbol_table.is_wide() { 2 } else { 1 };
+            assert_eq!(symbol_table.dump().len() - (Symbol::MAX_LEN - present) * entry_size + 1, dump.len());
+            let loaded_table = SymbolTableBuilder::load_from_bytes(&dump).unwrap();
+            assert_eq!(symbol_table.dump(), loaded_table.dump());
+            let (end_pos, decoder) = crate::core::codec::Decoder::from_table_bytes(&[&dump[..], b"rest"].concat()).unwrap();
+            assert_eq!(dump.len(), end_pos);
+            let encoding = Encoder::from_table(symbol_table).encode_str(test_str);
+            assert_eq!(test_str, decoder.decode(&encoding));
+            for len in 0..dump.len() {
+                assert!(SymbolTableBuilder::load_from_bytes(&dump[..len]).is_err());
+            }
+        }
+        // a table of 3 symbols of one length
+        let mut symbol_table = PerfectHashSymbolTable::new();
+        for s in [b"tu", b"mc", b"wi"] {
+            symbol_table.add_bytes(s);
+        }
+        symbol_table.finalize();
+        assert_eq!(symbol_table.dump().len() - 6, symbol_table.dump_compact().len());
+    }
+
+    #[test]
+    pub fn test_prune_unused() {
+        let strings = read_string_lines("assets/test_data/l_comment").unwrap();
+        let corpus = strings[..20].iter().map(|s| s.as_bytes()).collect::<Vec<&[u8]>>();
+        for wide in [false, true] {
+            let mut symbol_table = SymbolTableBuilder::new().wide_codes(wide).train_by_sampling(&strings);
+            let (len, dump_len) = (symbol_table.len(), symbol_table.dump_len());
+            let encoder = Encoder::from_table(&symbol_table);
+            let encoded_lens = corpus.iter().map(|s| encoder.encoded_len(s)).collect::<Vec<usize>>();
+
+            let removed = symbol_table.prune_unused(&corpus);
+            assert!(removed > 0);
+            assert_eq!(len - removed, symbol_table.len());
+            assert!(symbol_table.dump_len() < dump_len);
+            assert_eq!(0, symbol_table.prune_unused(&corpus));
+            let encoder = Encoder::from_table(&symbol_table);
+            let decoder = crate::core::codec::Decoder::from_table(&symbol_table);
+            for (s, encoded_len) in corpus.iter().zip(encoded_lens) {
+                let encoding = encoder.encode_bytes(s);
+                assert_eq!(encoded_len, encoding.len());
+                assert_eq!(*s, decoder.decode_bytes(&encoding).as_slice());
+            }
+        }
+        let mut identity_table = SymbolTableBuilder::identity_table();
+        assert_eq!(0, identity_table.prune_unused(&corpus));
+    }
+
+    #[test]
+    pub fn test_try_add_symbol() {
+        let test_str = "tumcwitumvldb";
+        let hot = b"zqzqzqzq";
+        for wide in [false, true] {
+            let dump = SymbolTableBuilder::new().wide_codes(wide).max_symbols(8).train_from(test_str).dump();
+            let mut symbol_table = SymbolTableBuilder::load_from_bytes(&dump).unwrap();
+            let symbols = symbol_table.iter_symbols().collect::<Vec<(u16, Vec<u8>)>>();
+            let encoding = Encoder::from_table(&symbol_table).encode_str(test_str);
+
+            let code = symbol_table.try_add_symbol(hot).unwrap();
+            assert_eq!(symbols.len() + 1, symbol_table.len());
+            assert_eq!(Some(code), symbol_table.try_add_symbol(hot));
+            assert_eq!(Some(symbols[0].0), symbol_table.try_add_symbol(&symbols[0].1));
+            assert_eq!(None, symbol_table.try_add_symbol(b""));
+            assert_eq!(None, symbol_table.try_add_symbol(b"123456789"));
+            // the codes of the symbols already in the table do not change
+            assert_eq!(symbols[..], symbol_table.iter_symbols().collect::<Vec<(u16, Vec<u8>)>>()[..symbols.len()]);
+            assert_eq!((code, hot.to_vec()), symbol_table.iter_symbols().last().unwrap());
+
+            let loaded_table = SymbolTableBuilder::load_from_bytes(&symbol_table.dump()).unwrap();
+            for table in [&symbol_table, &loaded_table] {
+                let decoder = crate::core::codec::Decoder::from_table(table);
+                assert_eq!(test_str, decoder.decode(&encoding));
+                let hot_encoding = Encoder::from_table(table).encode_bytes(hot);
+                assert_eq!(if wide { 2 } else { 1 }, hot_encoding.len());
+                assert_eq!(hot.to_vec(), decoder.decode_bytes(&hot_encoding));
+            }
+        }
+
+        // a shorter symbol would break the length order of a wide table
+        let mut wide_table = SymbolTableBuilder::new().wide_codes(true).train_from(test_str);
+        assert_eq!(None, wide_table.try_add_symbol(b"zq"));
+        assert_eq!(None, wide_table.try_add_symbol(b"z"));
+
+        // an unfinalized table is finalized in order, so its dump stores the symbol lengths
+        let mut symbol_table = PerfectHashSymbolTable::new();
+        symbol_table.add_bytes(b"tumc");
+        assert_eq!(Some(1), symbol_table.try_add_symbol(b"wi"));
+        let dump_len = symbol_table.dump_len();
+        assert_eq!(Some(2), symbol_table.try_add_symbol(b"vl"));
+        assert_eq!(dump_len + 3, symbol_table.dump_len());
+        // a symbol at least as long as the others keeps the codes ordered by length
+        let mut symbol_table = PerfectHashSymbolTable::new();
+        symbol_table.add_bytes(b"tumc");
+        symbol_table.add_bytes(b"wi");
+        symbol_table.finalize();
+        let dump_len = symbol_table.dump_len();
+        assert_eq!(Some(2), symbol_table.try_add_symbol(b"vldbvldb"));
+        assert_eq!(dump_len + 8, symbol_table.dump_len());
+
+        let mut full_table = PerfectHashSymbolTable::new();
+        for b in 0..CODE_ESCAPE {
+            full_table.add_bytes(&[b]);
+        }
+        full_table.finalize();
+        assert_eq!(None, full_table.try_add_symbol(b"zq"));
+        assert_eq!(None, SymbolTableBuilder::identity_table().try_add_symbol(b"zq"));
+    }
+
+    #[test]
+    pub fn test_estimate_compression_factor() {
+        let strings = read_string_lines("assets/test_data/l_comment").unwrap();
+        let (symbol_table, encodings) = encode_all_strings(&strings);
+        let sample = strings[0].as_bytes();
+        let factor = sample.len() as f64 / encodings[0].len() as f64;
+        assert_eq!(factor, symbol_table.estimate_compression_factor(sample));
+        assert_eq!(1.0, symbol_table.estimate_compression_factor(b""));
+        assert_eq!(1.0, SymbolTableBuilder::identity_table().estimate_compression_factor(sample));
+        let wide_table = SymbolTableBuilder::new().wide_codes(true).train_from(&strings[0]);
+        let encoding = Encoder::from_table(&wide_table).encode_str(&strings[0]);
+        assert_eq!(sample.len() as f64 / encoding.len() as f64, wide_table.estimate_compression_factor(sample));
+    }
+
+    #[test]
+    pub fn test_dump_len() {
+        let strings = read_string_lines("assets/test_data/l_comment").unwrap();
+        let (symbol_table, _) = encode_all_strings(&strings);
+        assert_eq!(symbol_table.dump().len(), symbol_table.dump_len());
+        let wide_table = SymbolTableBuilder::new().wide_codes(true).train_from(&strings[0]);
+        assert_eq!(wide_table.dump().len(), wide_table.dump_len());
+        let identity_table = SymbolTableBuilder::identity_table();
+        assert_eq!(identity_table.dump().len(), identity_table.dump_len());
+    }
+
+    #[test]
+    pub fn test_optimize_for_decode_speed() {
+        let mut strings = read_string_lines("assets/test_data/l_comment").unwrap();
+        strings.truncate(2000);
+        // rare bytes, which the ratio table leaves to escapes
+        strings.push(('\u{a0}'..='\u{7ff}').collect());
+        let inputs = strings.iter().map(|s| s.as_bytes()).collect::<Vec<&[u8]>>();
+        let ratio_table = SymbolTableBuilder::new().train_from_samples(&strings);
+        let speed_table = SymbolTableBuilder::new().optimize_for(OptimizeFor::DecodeSpeed).train_from_samples(&strings);
+        let ratio_escapes = Encoder::from_table(&ratio_table).usage_counts(&inputs)[255];
+        let speed_escapes = Encoder::from_table(&speed_table).usage_counts(&inputs)[255];
+        assert!(speed_escapes < ratio_escapes);
+        let decoder = crate::core::codec::Decoder::from_table(&speed_table);
+        let encoder = Encoder::from_table(&speed_table);
+        for str in &strings {
+            assert_eq!(*str, decoder.decode(&encoder.encode_str(str)));
+        }
+    }
+}
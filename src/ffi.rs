@@ -0,0 +1,187 @@
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::ptr;
+use core::slice;
+
+use crate::build_table_by_sampling;
+use crate::core::codec::{Decoder, Encoder};
+use crate::core::symbol_table::SymbolTable;
+
+/// the call succeeded and the output was written
+pub const FSST_OK: i32 = 0;
+/// the output buffer is too small, the needed length is written to `out_len` and nothing else
+pub const FSST_BUFFER_TOO_SMALL: i32 = -1;
+/// a pointer is null, a sample is not valid UTF-8 or an encoding is invalid
+pub const FSST_INVALID_ARGUMENT: i32 = -2;
+
+/// opaque handle of a symbol table for C callers, it holds the decoder as well so decoding does not
+/// rebuild it on every call
+pub struct FsstTable {
+    table: Box<dyn SymbolTable>,
+    decoder: Decoder,
+}
+
+/// build a symbol table by sampling `count` strings, see `build_table_by_sampling`
+/// the string `i` starts at `strings[i]` and is `lens[i]` bytes long
+/// returns null if a pointer is null or a string is not valid UTF-8, the table must be released with
+/// `fsst_table_free`
+///
+/// # Safety
+/// `strings` and `lens` must point to `count` elements each, and every string must be readable for
+/// its length
+#[no_mangle]
+pub unsafe extern "C" fn fsst_table_new_from_sample(
+    strings: *const *const u8,
+    lens: *const usize,
+    count: usize,
+) -> *mut FsstTable {
+    if count > 0 && (strings.is_null() || lens.is_null()) {
+        return ptr::null_mut();
+    }
+    let mut samples = Vec::with_capacity(count);
+    for i in 0..count {
+        let Some(bytes) = as_slice(*strings.add(i), *lens.add(i)) else {
+            return ptr::null_mut();
+        };
+        match core::str::from_utf8(bytes) {
+            Ok(str) => samples.push(str),
+            Err(_) => return ptr::null_mut(),
+        }
+    }
+    let table = build_table_by_sampling(&samples);
+    let decoder = Decoder::from_table(&table);
+    Box::into_raw(Box::new(FsstTable { table, decoder }))
+}
+
+/// encode `input_len` bytes into the caller's buffer of `out_cap` bytes, see `Encoder::encode_bytes`
+/// the encoding takes at most twice the input length, the length it takes is written to `out_len`
+/// returns `FSST_OK`, `FSST_BUFFER_TOO_SMALL` or `FSST_INVALID_ARGUMENT`
+///
+/// # Safety
+/// `table` must come from `fsst_table_new_from_sample` and not be freed yet, `input` must be
+/// readable for `input_len` bytes, `out` writable for `out_cap` bytes and `out_len` writable
+#[no_mangle]
+pub unsafe extern "C" fn fsst_encode(
+    table: *const FsstTable,
+    input: *const u8,
+    input_len: usize,
+    out: *mut u8,
+    out_cap: usize,
+    out_len: *mut usize,
+) -> i32 {
+    let (Some(table), Some(input)) = (table.as_ref(), as_slice(input, input_len)) else {
+        return FSST_INVALID_ARGUMENT;
+    };
+    let encoding = Encoder::from_table(&table.table).encode_bytes(input);
+    write_out(&encoding, out, out_cap, out_len)
+}
+
+/// decode `input_len` bytes encoded with the same table into the caller's buffer of `out_cap`
+/// bytes, see `Decoder::decode_checked`
+/// the decoded bytes take at most eight times the encoding length, the length they take is written
+/// to `out_len`
+/// returns `FSST_OK`, `FSST_BUFFER_TOO_SMALL` or `FSST_INVALID_ARGUMENT`, which includes an
+/// encoding with a code not in the table
+///
+/// # Safety
+/// `table` must come from `fsst_table_new_from_sample` and not be freed yet, `input` must be
+/// readable for `input_len` bytes, `out` writable for `out_cap` bytes and `out_len` writable
+#[no_mangle]
+pub unsafe extern "C" fn fsst_decode(
+    table: *const FsstTable,
+    input: *const u8,
+    input_len: usize,
+    out: *mut u8,
+    out_cap: usize,
+    out_len: *mut usize,
+) -> i32 {
+    let (Some(table), Some(input)) = (table.as_ref(), as_slice(input, input_len)) else {
+        return FSST_INVALID_ARGUMENT;
+    };
+    match table.decoder.decode_checked(input) {
+        Ok(decoded) => write_out(&decoded, out, out_cap, out_len),
+        Err(_) => FSST_INVALID_ARGUMENT,
+    }
+}
+
+/// release a table built by `fsst_table_new_from_sample`, a null table is ignored
+///
+/// # Safety
+/// `table` must come from `fsst_table_new_from_sample` and must not be used or freed again
+#[no_mangle]
+pub unsafe extern "C" fn fsst_table_free(table: *mut FsstTable) {
+    if !table.is_null() {
+        drop(Box::from_raw(table));
+    }
+}
+
+/// a null pointer is only accepted for an empty slice
+unsafe fn as_slice<'a>(data: *const u8, len: usize) -> Option<&'a [u8]> {
+    if len == 0 {
+        Some(&[])
+    } else if data.is_null() {
+        None
+    } else {
+        Some(slice::from_raw_parts(data, len))
+    }
+}
+
+unsafe fn write_out(bytes: &[u8], out: *mut u8, out_cap: usize, out_len: *mut usize) -> i32 {
+    if out_len.is_null() || (out.is_null() && !bytes.is_empty()) {
+        return FSST_INVALID_ARGUMENT;
+    }
+    *out_len = bytes.len();
+    if bytes.len() > out_cap {
+        return FSST_BUFFER_TOO_SMALL;
+    }
+    ptr::copy_nonoverlapping(bytes.as_ptr(), out, bytes.len());
+    FSST_OK
+}
+
+#[cfg(test)]
+mod test {
+    use core::ptr;
+
+    use crate::ffi::{
+        fsst_decode, fsst_encode, fsst_table_free, fsst_table_new_from_sample, FSST_BUFFER_TOO_SMALL,
+        FSST_INVALID_ARGUMENT, FSST_OK,
+    };
+
+    #[test]
+    pub fn test_round_trip() {
+        let strings = crate::read_string_lines("assets/test_data/c_name").unwrap();
+        let strings = &strings[..400];
+        let ptrs = strings.iter().map(|s| s.as_ptr()).collect::<Vec<*const u8>>();
+        let lens = strings.iter().map(|s| s.len()).collect::<Vec<usize>>();
+        unsafe {
+            let table = fsst_table_new_from_sample(ptrs.as_ptr(), lens.as_ptr(), strings.len());
+            assert!(!table.is_null());
+            let (mut encoding, mut decoded, mut len) = (vec![0u8; 256], vec![0u8; 1024], 0usize);
+            for str in strings {
+                let input = str.as_bytes();
+                assert_eq!(FSST_OK, fsst_encode(table, input.as_ptr(), input.len(), encoding.as_mut_ptr(), 256, &mut len));
+                assert!(len < input.len());
+                let encoding = &encoding[..len];
+                assert_eq!(
+                    FSST_OK,
+                    fsst_decode(table, encoding.as_ptr(), encoding.len(), decoded.as_mut_ptr(), 1024, &mut len)
+                );
+                assert_eq!(input, &decoded[..len]);
+            }
+
+            let input = strings[0].as_bytes();
+            assert_eq!(FSST_BUFFER_TOO_SMALL, fsst_encode(table, input.as_ptr(), input.len(), encoding.as_mut_ptr(), 1, &mut len));
+            assert!(len > 1);
+            assert_eq!(FSST_OK, fsst_encode(table, ptr::null(), 0, ptr::null_mut(), 0, &mut len));
+            assert_eq!(0, len);
+            assert_eq!(FSST_INVALID_ARGUMENT, fsst_encode(table, ptr::null(), 1, encoding.as_mut_ptr(), 256, &mut len));
+            assert_eq!(FSST_INVALID_ARGUMENT, fsst_decode(table, [255u8].as_ptr(), 1, decoded.as_mut_ptr(), 1024, &mut len));
+            fsst_table_free(table);
+            fsst_table_free(ptr::null_mut());
+
+            let invalid_utf8 = [0xc3u8, 0x28];
+            assert!(fsst_table_new_from_sample(&invalid_utf8.as_ptr(), &2, 1).is_null());
+            assert!(fsst_table_new_from_sample(ptr::null(), ptr::null(), 1).is_null());
+        }
+    }
+}
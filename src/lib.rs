@@ -1,14 +1,33 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
 use std::fs::File;
+#[cfg(feature = "std")]
 use std::io;
+#[cfg(feature = "std")]
 use std::io::BufRead;
+#[cfg(feature = "std")]
 use std::path::Path;
 
 use crate::core::codec::{Decoder, Encoder};
+use crate::core::error::EncodeError;
 use crate::core::symbol_table::{SymbolTable, SymbolTableBuilder};
-use crate::core::take_sample;
+use crate::core::{take_sample_with_seed, DEFAULT_SAMPLE_SEED};
 
 pub mod core;
 mod util;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+
+pub use crate::core::take_sample_from_bytes;
+pub use crate::util::endian::Endian;
 
 /// build symbol table by sampling the given strings
 /// symbol table can be used to build `Encoder` and `Decoder`
@@ -26,26 +45,161 @@ mod util;
 /// let decode_str = decoder.decode(&encoding);
 /// assert_eq!(str, decode_str);
 /// ```
-pub fn build_table_by_sampling(strings: &Vec<String>) -> Box<dyn SymbolTable> {
-    let sample = take_sample(&strings);
-    SymbolTableBuilder::build_from_samples(&sample)
+pub fn build_table_by_sampling<S: AsRef<str>>(strings: &[S]) -> Box<dyn SymbolTable> {
+    build_table_by_sampling_with_seed(strings, DEFAULT_SAMPLE_SEED)
+}
+
+/// build symbol table like `build_table_by_sampling`, but sample with the given seed
+/// the same strings and seed always build a byte-identical table
+pub fn build_table_by_sampling_with_seed<S: AsRef<str>>(strings: &[S], seed: u64) -> Box<dyn SymbolTable> {
+    SymbolTableBuilder::new().seed(seed).train_by_sampling(strings)
+}
+
+/// build symbol table like `build_table_by_sampling` and also return the sampled strings it was
+/// trained on, so the training input can be inspected or the table reproduced with
+/// `SymbolTableBuilder::build_from_samples`
+pub fn build_table_by_sampling_with_sample<S: AsRef<str>>(strings: &[S]) -> (Box<dyn SymbolTable>, Vec<String>) {
+    let sample = take_sample_with_seed(strings, DEFAULT_SAMPLE_SEED)
+        .into_iter()
+        .map(|s| String::from(s.as_ref()))
+        .collect::<Vec<String>>();
+    (SymbolTableBuilder::build_from_samples(&sample), sample)
+}
+
+/// build one symbol table for several columns, every column is sampled in proportion to its size
+/// and the table can encode any of them, which saves storing a table per column when their
+/// vocabulary overlaps
+pub fn build_shared_table<S: AsRef<str>>(columns: &[&[S]]) -> Box<dyn SymbolTable> {
+    SymbolTableBuilder::new().train_shared_by_sampling(columns)
+}
+
+/// build symbol table for one large buffer, such as a whole binary file, by sampling segments spread
+/// over it with `take_sample_from_bytes` instead of splitting it into lines first
+pub fn build_table_from_bytes_sampling(bytes: &[u8]) -> Box<dyn SymbolTable> {
+    SymbolTableBuilder::build_by_sampling_bytes(bytes)
 }
 
 /// encode all given strings
 /// it will sample the given strings and build a symbol table which will be returned in a tuple
-pub fn encode_all_strings(strings: &Vec<String>) -> (Box<dyn SymbolTable>, Vec<Vec<u8>>) {
+/// if the encodings are not smaller than the strings, the strings are stored verbatim and the
+/// returned table is the identity table instead of the trained one, check `is_identity` on it
+/// there is no per-string marker byte, the stored mode is recorded once in the dump of the
+/// returned table, so the encodings must be decoded with a decoder of that table
+pub fn encode_all_strings<S: AsRef<str>>(strings: &[S]) -> (Box<dyn SymbolTable>, Vec<Vec<u8>>) {
     let symbol_table = build_table_by_sampling(strings);
-    let encoder = Encoder::from_table(&symbol_table);
+    let encodings = encode_all_strings_with_table(&symbol_table, strings);
+    store_if_incompressible(strings, symbol_table, encodings)
+}
+
+/// encode all given strings with a previously built symbol table instead of training a new one,
+/// so batches sharing vocabulary can share one table
+#[allow(clippy::borrowed_box)]
+pub fn encode_all_strings_with_table<S: AsRef<str>>(table: &Box<dyn SymbolTable>, strings: &[S]) -> Vec<Vec<u8>> {
+    let encoder = Encoder::from_table(table);
     let mut encodings = Vec::with_capacity(strings.len());
+    let mut buf = Vec::new();
     for str in strings {
-        encodings.push(encoder.encode_str(str));
+        encoder.encode_into(str.as_ref().as_bytes(), &mut buf);
+        encodings.push(buf.clone());
+    }
+    encodings
+}
+
+/// encode one contiguous buffer with a symbol table, such as one built by
+/// `build_table_from_bytes_sampling`, without starting over at every record
+/// symbols can span the record boundaries, which saves bytes over encoding the records one by one
+/// with the same table, but no record can be decoded without decoding the buffer
+#[allow(clippy::borrowed_box)]
+pub fn encode_buffer(table: &Box<dyn SymbolTable>, buf: &[u8]) -> Vec<u8> {
+    Encoder::from_table(table).encode_bytes(buf)
+}
+
+/// fraction of the sample bytes the table encodes with symbols longer than one byte, a low
+/// coverage means the table leaves most of the sample to single byte codes and escapes
+/// an empty sample has a coverage of 0
+#[allow(clippy::borrowed_box)]
+pub fn coverage(table: &Box<dyn SymbolTable>, sample: &[u8]) -> f64 {
+    if sample.is_empty() {
+        return 0.0;
     }
-    (symbol_table, encodings)
+    Encoder::from_table(table).covered_bytes(sample) as f64 / sample.len() as f64
+}
+
+/// fall back to the identity table when the encodings have no net gain over the strings
+#[allow(clippy::type_complexity)]
+fn store_if_incompressible<S: AsRef<str>>(
+    strings: &[S],
+    symbol_table: Box<dyn SymbolTable>,
+    encodings: Vec<Vec<u8>>,
+) -> (Box<dyn SymbolTable>, Vec<Vec<u8>>) {
+    let input_bytes = strings.iter().map(|s| s.as_ref().len()).sum::<usize>();
+    let encoded_bytes = encodings.iter().map(|e| e.len()).sum::<usize>();
+    // a table without symbols escapes every byte, which never pays off
+    if !symbol_table.is_empty() && encoded_bytes < input_bytes {
+        return (symbol_table, encodings);
+    }
+    let stored = strings.iter().map(|s| s.as_ref().as_bytes().to_vec()).collect();
+    (SymbolTableBuilder::identity_table(), stored)
+}
+
+/// encode all given strings like `encode_all_strings`, but encode the strings in parallel
+#[cfg(feature = "rayon")]
+pub fn encode_all_strings_parallel<S: AsRef<str> + Sync>(strings: &[S]) -> (Box<dyn SymbolTable>, Vec<Vec<u8>>) {
+    use rayon::prelude::*;
+
+    let symbol_table = build_table_by_sampling(strings);
+    let encoder = Encoder::from_table(&symbol_table);
+    let encodings = strings
+        .par_iter()
+        .map_init(Vec::new, |buf, str| {
+            encoder.encode_into(str.as_ref().as_bytes(), buf);
+            buf.clone()
+        })
+        .collect();
+    store_if_incompressible(strings, symbol_table, encodings)
+}
+
+/// statistics of encoding a batch of strings with a single symbol table
+#[derive(Clone, Copy, Debug)]
+pub struct CompressionStats {
+    /// total size of the input strings
+    pub input_bytes: usize,
+    /// total size of the string encodings, excluding the symbol table
+    pub encoded_bytes: usize,
+    /// size of the dumped symbol table
+    pub table_bytes: usize,
+    /// input_bytes / (encoded_bytes + table_bytes)
+    pub compression_factor: f64,
+    /// number of symbols in the symbol table
+    pub symbol_count: usize,
+}
+
+/// encode all given strings like `encode_all_strings`, additionally reporting the compression
+/// statistics
+#[allow(clippy::type_complexity)]
+pub fn encode_all_strings_with_stats<S: AsRef<str>>(
+    strings: &[S],
+) -> (Box<dyn SymbolTable>, Vec<Vec<u8>>, CompressionStats) {
+    let (symbol_table, encodings) = encode_all_strings(strings);
+    let input_bytes = strings.iter().map(|s| s.as_ref().len()).sum::<usize>();
+    let encoded_bytes = encodings.iter().map(|e| e.len()).sum::<usize>();
+    let table_bytes = symbol_table.dump_len();
+    let stats = CompressionStats {
+        input_bytes,
+        encoded_bytes,
+        table_bytes,
+        compression_factor: input_bytes as f64 / (encoded_bytes + table_bytes) as f64,
+        symbol_count: symbol_table.len(),
+    };
+    (symbol_table, encodings, stats)
 }
 
 /// encode a single string
 /// if including_table is true, it will encode the symbol table to bytes
 /// and add it the encoding bytes header, i.e., | symbol table bytes | string encoding bytes |
+/// if the encoding has no net gain, the string is stored verbatim and the returned table is the
+/// identity table, whose dump marks the payload as uncompressed instead of a sentinel byte
+/// without the table included, the caller has to keep the returned table to decode the string
 /// # Example
 ///
 /// ```
@@ -53,52 +207,149 @@ pub fn encode_all_strings(strings: &Vec<String>) -> (Box<dyn SymbolTable>, Vec<V
 /// use fsst_rust::encode_string;
 /// let str = "hello world".to_string();
 /// let (_, encoding) = encode_string(&str, true);
-/// let (table_end_pos, decoder) = Decoder::from_table_bytes(&encoding);
-/// let decode_str = decoder.decode(&encoding[table_end_pos..].to_vec());
+/// let (table_end_pos, decoder) = Decoder::from_table_bytes(&encoding).unwrap();
+/// let decode_str = decoder.decode(&encoding[table_end_pos..]);
 /// assert_eq!(str, decode_str);
 /// ```
 pub fn encode_string(str: &str, including_table: bool) -> (Box<dyn SymbolTable>, Vec<u8>) {
     let symbol_table = SymbolTableBuilder::build_from(str);
     let encoder = Encoder::from_table(&symbol_table);
     let encoding = encoder.encode(str, including_table);
-    (symbol_table, encoding)
+    let identity_table = SymbolTableBuilder::identity_table();
+    let table_bytes = if including_table { identity_table.dump_len() } else { 0 };
+    if encoding.len() < table_bytes + str.len() {
+        return (symbol_table, encoding);
+    }
+    let encoding = Encoder::from_table(&identity_table).encode(str, including_table);
+    (identity_table, encoding)
+}
+
+/// encode a single string with its symbol table included like `encode_string`, but fail if the
+/// dumped symbol table is larger than `max_table_bytes`
+/// this lets a caller decide between an inline table and a shared one before writing anything
+pub fn try_encode_string(str: &str, max_table_bytes: usize) -> Result<(Box<dyn SymbolTable>, Vec<u8>), EncodeError> {
+    let (symbol_table, encoding) = encode_string(str, true);
+    let table_bytes = symbol_table.dump_len();
+    if table_bytes > max_table_bytes {
+        return Err(EncodeError::TableTooLarge(table_bytes));
+    }
+    Ok((symbol_table, encoding))
 }
 
 /// decode bytes to string according to the give symbol table
-pub fn decode_string(table: &Box<dyn SymbolTable>, encoding: &Vec<u8>) -> String {
+#[allow(clippy::borrowed_box)]
+pub fn decode_string(table: &Box<dyn SymbolTable>, encoding: &[u8]) -> String {
     Decoder::from_table(table).decode(encoding)
 }
 
 /// decode all string encodings by the given symbol table
+#[allow(clippy::borrowed_box)]
 pub fn decode_all_strings(table: &Box<dyn SymbolTable>, encodings: &Vec<Vec<u8>>) -> Vec<String> {
     let mut strings = Vec::with_capacity(encodings.len());
     let decoder = Decoder::from_table(table);
+    let mut buf = Vec::new();
     for encoding in encodings {
-        strings.push(decoder.decode(encoding))
+        decoder.decode_into(encoding, &mut buf);
+        strings.push(unsafe { String::from_utf8_unchecked(buf.clone()) })
     }
     strings
 }
 
+/// decode all string encodings like `decode_all_strings`, but decode the encodings in parallel
+#[cfg(feature = "rayon")]
+#[allow(clippy::borrowed_box)]
+pub fn decode_all_strings_parallel(table: &Box<dyn SymbolTable>, encodings: &Vec<Vec<u8>>) -> Vec<String> {
+    use rayon::prelude::*;
+
+    let decoder = Decoder::from_table(table);
+    encodings
+        .par_iter()
+        .map_init(Vec::new, |buf, encoding| {
+            decoder.decode_into(encoding, buf);
+            unsafe { String::from_utf8_unchecked(buf.clone()) }
+        })
+        .collect()
+}
+
+#[cfg(feature = "std")]
+#[allow(clippy::type_complexity)]
 pub fn encode_all_strings_from_file<P: AsRef<Path>>(filename: P) -> io::Result<(Box<dyn SymbolTable>, Vec<Vec<u8>>)> {
     let strings = read_string_lines(filename)?;
     Ok(encode_all_strings(&strings))
 }
 
+/// compress a UTF-8 text file line by line into a self-describing block written to `output`, see
+/// `core::block::write_block`
+/// the newlines are kept in the records, so `decompress_file_to` restores the file byte for byte
+/// it returns an `InvalidData` error if the file is not valid UTF-8
+#[cfg(feature = "std")]
+pub fn compress_file_to<P: AsRef<Path>, Q: AsRef<Path>>(input: P, output: Q) -> io::Result<CompressionStats> {
+    let text = std::fs::read_to_string(input)?;
+    let records = text.split_inclusive('\n').collect::<Vec<&str>>();
+    let (symbol_table, encodings, stats) = encode_all_strings_with_stats(&records);
+    std::fs::write(output, crate::core::block::write_block(&symbol_table, &encodings))?;
+    Ok(stats)
+}
+
+/// decompress a file written by `compress_file_to` into `output`
+/// it returns an `InvalidData` error if the block can not be read
+#[cfg(feature = "std")]
+pub fn decompress_file_to<P: AsRef<Path>, Q: AsRef<Path>>(input: P, output: Q) -> io::Result<()> {
+    let block = std::fs::read(input)?;
+    let (symbol_table, encodings) =
+        crate::core::block::read_block(&block).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let decoder = Decoder::from_table(&symbol_table);
+    let mut text = Vec::with_capacity(encodings.iter().map(|e| e.len()).sum::<usize>() * 4);
+    for encoding in &encodings {
+        text.extend_from_slice(&decoder.decode_checked(encoding).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?);
+    }
+    std::fs::write(output, text)
+}
+
+/// read all lines of the given file
+/// it returns an `InvalidData` error if any line is not valid UTF-8
+#[cfg(feature = "std")]
 pub fn read_string_lines<P>(filename: P) -> io::Result<Vec<String>>
 where
     P: AsRef<Path>,
 {
     let file = File::open(filename)?;
-    let strings: Vec<String> = io::BufReader::new(file)
-        .lines()
-        .map(|l| l.expect("read string failed"))
-        .collect();
-    Ok(strings)
+    io::BufReader::new(file).lines().collect()
+}
+
+/// read all lines of the given file, replacing invalid UTF-8 sequences with `U+FFFD`
+#[cfg(feature = "std")]
+pub fn read_string_lines_lossy<P>(filename: P) -> io::Result<Vec<String>>
+where
+    P: AsRef<Path>,
+{
+    let file = File::open(filename)?;
+    io::BufReader::new(file)
+        .split(b'\n')
+        .map(|l| {
+            let mut line = l?;
+            if line.last() == Some(&b'\r') {
+                line.pop();
+            }
+            Ok(String::from_utf8_lossy(&line).into_owned())
+        })
+        .collect()
 }
 
 #[cfg(test)]
 mod test {
-    use crate::{decode_all_strings, encode_all_strings, read_string_lines};
+    use std::io::ErrorKind;
+
+    use crate::{
+        build_shared_table, build_table_by_sampling, build_table_by_sampling_with_sample,
+        build_table_by_sampling_with_seed, build_table_from_bytes_sampling, compress_file_to, coverage,
+        decode_all_strings, decompress_file_to,
+        decode_string, encode_all_strings, encode_buffer, encode_all_strings_with_stats,
+        encode_all_strings_with_table, encode_string, read_string_lines, read_string_lines_lossy,
+        try_encode_string,
+    };
+    use crate::core::codec::Decoder;
+    use crate::core::symbol_table::SymbolTableBuilder;
 
     #[test]
     pub fn test_codec() {
@@ -111,5 +362,226 @@ mod test {
             assert_eq!(strings[i], decode_strings[i]);
         }
     }
-}
 
+    #[cfg(feature = "rayon")]
+    #[test]
+    pub fn test_parallel_encode() {
+        let mut strings = read_string_lines("assets/test_data/c_name").unwrap();
+        strings.truncate(1000);
+        let (table, encodings) = crate::encode_all_strings_parallel(&strings);
+        assert_eq!(encode_all_strings(&strings).1, encodings);
+        let decode_strings = decode_all_strings(&table, &encodings);
+        assert_eq!(strings, decode_strings);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    pub fn test_parallel_decode() {
+        let mut strings = read_string_lines("assets/test_data/l_comment").unwrap();
+        strings.truncate(1000);
+        let (table, encodings) = encode_all_strings(&strings);
+        let decode_strings = crate::decode_all_strings_parallel(&table, &encodings);
+        assert_eq!(strings, decode_strings);
+    }
+
+    #[test]
+    pub fn test_compression_stats() {
+        let mut strings = read_string_lines("assets/test_data/c_name").unwrap();
+        strings.truncate(1000);
+        let (table, encodings, stats) = encode_all_strings_with_stats(&strings);
+        assert_eq!(strings.iter().map(|s| s.len()).sum::<usize>(), stats.input_bytes);
+        assert_eq!(encodings.iter().map(|e| e.len()).sum::<usize>(), stats.encoded_bytes);
+        assert_eq!(table.dump().len(), stats.table_bytes);
+        assert_eq!(table.len(), stats.symbol_count);
+        assert!(stats.compression_factor > 1.0);
+    }
+
+    #[test]
+    pub fn test_compress_file_to() {
+        let dir = std::env::temp_dir();
+        let (compressed, decompressed) = (dir.join("fsst_rust_compress_file_to.fsst"), dir.join("fsst_rust_compress_file_to"));
+        let input = "assets/test_data/c_name";
+        let stats = compress_file_to(input, &compressed).unwrap();
+        let text = std::fs::read(input).unwrap();
+        assert_eq!(text.len(), stats.input_bytes);
+        assert!(stats.compression_factor > 1.0);
+        assert!((std::fs::metadata(&compressed).unwrap().len() as usize) < text.len());
+        decompress_file_to(&compressed, &decompressed).unwrap();
+        assert_eq!(text, std::fs::read(&decompressed).unwrap());
+
+        // no trailing newline and an empty file
+        for text in ["tumcwitumvldb\ntumcwitumvldb", ""] {
+            std::fs::write(&decompressed, text).unwrap();
+            compress_file_to(&decompressed, &compressed).unwrap();
+            decompress_file_to(&compressed, &decompressed).unwrap();
+            assert_eq!(text, std::fs::read_to_string(&decompressed).unwrap());
+        }
+
+        std::fs::write(&compressed, b"not fsst").unwrap();
+        assert_eq!(ErrorKind::InvalidData, decompress_file_to(&compressed, &decompressed).unwrap_err().kind());
+        std::fs::remove_file(compressed).unwrap();
+        std::fs::remove_file(decompressed).unwrap();
+    }
+
+    #[test]
+    pub fn test_read_invalid_utf8_lines() {
+        let path = std::env::temp_dir().join("fsst_rust_invalid_utf8_lines");
+        std::fs::write(&path, b"hello\r\nwor\xffld\n").unwrap();
+        let err = read_string_lines(&path).unwrap_err();
+        assert_eq!(ErrorKind::InvalidData, err.kind());
+        let strings = read_string_lines_lossy(&path).unwrap();
+        assert_eq!(vec!["hello".to_string(), "wor\u{fffd}ld".to_string()], strings);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    pub fn test_store_incompressible() {
+        let mut rand = 42u64;
+        let random_str = (0..1000)
+            .map(|_| {
+                rand = crate::core::xorshift(rand);
+                (b' ' + (rand % 95) as u8) as char
+            })
+            .collect::<String>();
+        let (table, encoding) = encode_string(&random_str, true);
+        assert!(table.is_identity());
+        let (table_end_pos, decoder) = Decoder::from_table_bytes(&encoding).unwrap();
+        assert!(decoder.is_identity());
+        assert_eq!(random_str.as_bytes(), &encoding[table_end_pos..]);
+        assert_eq!(random_str, decoder.decode(&encoding[table_end_pos..]));
+
+        let (table, encoding) = encode_string("tumcwitumvldbtumcwitumvldb", true);
+        assert!(!table.is_identity());
+        assert!(encoding.len() < table.dump().len() + 26);
+
+        let strings = random_str.as_bytes().chunks(10).map(|c| String::from_utf8(c.to_vec()).unwrap()).collect::<Vec<String>>();
+        let (table, encodings) = encode_all_strings(&strings);
+        assert!(table.is_identity());
+        assert_eq!(strings, decode_all_strings(&table, &encodings));
+    }
+
+    #[test]
+    pub fn test_try_encode_string() {
+        let test_str = "tumcwitumvldbtumcwitumvldb";
+        let (table, encoding) = encode_string(test_str, true);
+        let table_bytes = table.dump().len();
+        let (_, try_encoding) = try_encode_string(test_str, table_bytes).unwrap();
+        assert_eq!(encoding, try_encoding);
+        let err = try_encode_string(test_str, table_bytes - 1).err().unwrap();
+        assert_eq!(crate::core::error::EncodeError::TableTooLarge(table_bytes), err);
+    }
+
+    #[test]
+    pub fn test_encode_borrowed_strings() {
+        let mut strings = read_string_lines("assets/test_data/c_name").unwrap();
+        strings.truncate(1000);
+        let borrowed = strings.iter().map(|s| s.as_str()).collect::<Vec<&str>>();
+        let (table, encodings) = encode_all_strings(&borrowed);
+        assert_eq!(encode_all_strings(&strings).1, encodings);
+        assert_eq!(strings, decode_all_strings(&table, &encodings));
+    }
+
+    #[test]
+    pub fn test_empty_strings() {
+        for including_table in [false, true] {
+            let (table, encoding) = encode_string("", including_table);
+            assert_eq!(table.dump().len() * including_table as usize, encoding.len());
+            assert_eq!("", decode_string(&table, &Vec::new()));
+        }
+
+        let (table, encodings) = encode_all_strings(&vec![String::new(); 3]);
+        assert_eq!(vec![Vec::<u8>::new(); 3], encodings);
+        assert_eq!(vec![String::new(); 3], decode_all_strings(&table, &encodings));
+
+        let mut strings = read_string_lines("assets/test_data/c_name").unwrap();
+        strings.truncate(100);
+        for i in (0..strings.len()).step_by(3) {
+            strings[i].clear();
+        }
+        let (table, encodings) = encode_all_strings(&strings);
+        assert!(!table.is_identity());
+        assert_eq!(strings, decode_all_strings(&table, &encodings));
+    }
+
+    #[test]
+    pub fn test_build_table_with_seed() {
+        let strings = read_string_lines("assets/test_data/l_comment").unwrap();
+        let table = build_table_by_sampling_with_seed(&strings, 42);
+        assert_eq!(table.dump(), build_table_by_sampling_with_seed(&strings, 42).dump());
+        assert_ne!(table.dump(), build_table_by_sampling_with_seed(&strings, 43).dump());
+        let default_table = build_table_by_sampling(&strings);
+        assert_eq!(default_table.dump(), build_table_by_sampling_with_seed(&strings, crate::core::DEFAULT_SAMPLE_SEED).dump());
+    }
+
+    #[test]
+    pub fn test_build_table_by_sampling_with_sample() {
+        let strings = read_string_lines("assets/test_data/l_comment").unwrap();
+        let (table, sample) = build_table_by_sampling_with_sample(&strings);
+        assert_eq!(build_table_by_sampling(&strings).dump(), table.dump());
+        assert_eq!(SymbolTableBuilder::build_from_samples(&sample).dump(), table.dump());
+        assert!(!sample.is_empty() && sample.len() < strings.len());
+        assert!(sample.iter().all(|s| strings.contains(s)));
+
+        let (_, sample) = build_table_by_sampling_with_sample(&strings[..10]);
+        assert_eq!(&strings[..10], sample.as_slice());
+    }
+
+    #[test]
+    pub fn test_encode_buffer() {
+        let strings = read_string_lines("assets/test_data/ps_comment").unwrap();
+        let strings = &strings[..1000];
+        let buffer = strings.concat();
+        let table = build_table_from_bytes_sampling(buffer.as_bytes());
+        let encoding = encode_buffer(&table, buffer.as_bytes());
+        assert_eq!(buffer, decode_string(&table, &encoding));
+        assert_eq!(Vec::<u8>::new(), encode_buffer(&table, b""));
+        // with the same table, symbols spanning the line ends save bytes
+        let (table, encodings) = encode_all_strings(strings);
+        let encoding = encode_buffer(&table, buffer.as_bytes());
+        assert!(encoding.len() < encodings.iter().map(|e| e.len()).sum::<usize>());
+    }
+
+    #[test]
+    pub fn test_coverage() {
+        let strings = read_string_lines("assets/test_data/ps_comment").unwrap();
+        let strings = &strings[..1000];
+        let table = build_table_by_sampling(strings);
+        let sample = strings.concat();
+        let covered = coverage(&table, sample.as_bytes());
+        assert!(covered > 0.5 && covered <= 1.0);
+        // bytes without any structure are left to single byte codes and escapes
+        let noise = (0..=255u8).rev().cycle().step_by(7).take(sample.len()).collect::<Vec<u8>>();
+        assert!(coverage(&table, &noise) < covered);
+        assert_eq!(0.0, coverage(&table, b""));
+    }
+
+    #[test]
+    pub fn test_build_shared_table() {
+        let l_comment = read_string_lines("assets/test_data/l_comment").unwrap();
+        let ps_comment = read_string_lines("assets/test_data/ps_comment").unwrap();
+        let columns = [&l_comment[..20000], &ps_comment[..5000]];
+        let table = build_shared_table(&columns);
+        assert_eq!(table.dump(), build_shared_table(&columns).dump());
+        // a single column takes the whole sample
+        assert_eq!(build_table_by_sampling(&l_comment).dump(), build_shared_table(&[&l_comment[..]]).dump());
+        for column in columns {
+            let encodings = encode_all_strings_with_table(&table, column);
+            assert_eq!(column, decode_all_strings(&table, &encodings).as_slice());
+            let input_bytes = column.iter().map(|s| s.len()).sum::<usize>();
+            assert!(encodings.iter().map(|e| e.len()).sum::<usize>() * 2 < input_bytes);
+        }
+        assert!(build_shared_table::<String>(&[&[], &[]]).is_empty());
+    }
+
+    #[test]
+    pub fn test_encode_with_shared_table() {
+        let strings = read_string_lines("assets/test_data/c_name").unwrap();
+        let (table, _) = encode_all_strings(&strings[..1000]);
+        let loaded_table = SymbolTableBuilder::load_from_bytes(&table.dump()).unwrap();
+        for batch in strings[1000..2000].chunks(100) {
+            let encodings = encode_all_strings_with_table(&table, batch);
+            assert_eq!(encodings, encode_all_strings_with_table(&loaded_table, batch));
+            assert_eq!(batch, decode_all_strings(&loaded_table, &encodings).as_slice());
+        }
+    }
+}
@@ -0,0 +1,40 @@
+// reflected Castagnoli polynomial
+const CRC32C_POLY: u32 = 0x82f6_3b78;
+const CRC32C_TABLE: [u32; 256] = crc32c_table();
+
+const fn crc32c_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ CRC32C_POLY } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+/// CRC-32C (Castagnoli) of the bytes, computed a byte at a time with a lookup table
+pub fn crc32c(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &b in bytes {
+        crc = CRC32C_TABLE[((crc ^ b as u32) & 0xff) as usize] ^ (crc >> 8);
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod test {
+    use crate::util::crc32c::crc32c;
+
+    #[test]
+    pub fn test_crc32c() {
+        assert_eq!(0, crc32c(b""));
+        assert_eq!(0xe306_9283, crc32c(b"123456789"));
+        assert_eq!(0x8a91_36aa, crc32c(&[0u8; 32]));
+    }
+}
@@ -1,15 +1,18 @@
-#[derive(PartialEq, Eq)]
+use crate::core::error::DecodeError;
+
+/// byte order of a dumped symbol table
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Endian {
     Little,
     Big,
 }
 
 impl Endian {
-    pub fn from_u8(b: u8) -> Endian {
+    pub fn from_u8(b: u8) -> Result<Endian, DecodeError> {
         match b {
-            0u8 => Endian::Little,
-            1u8 => Endian::Big,
-            _ => panic!("unknown endian byte {}", b)
+            0u8 => Ok(Endian::Little),
+            1u8 => Ok(Endian::Big),
+            _ => Err(DecodeError::UnknownEndian(b))
         }
     }
 
@@ -25,9 +28,9 @@ impl Endian {
     }
 }
 
-impl Into<u8> for Endian {
-    fn into(self) -> u8 {
-        match self {
+impl From<Endian> for u8 {
+    fn from(endian: Endian) -> u8 {
+        match endian {
             Endian::Little => 0u8,
             Endian::Big => 1u8
         }
@@ -1 +1,3 @@
-pub mod endian;
\ No newline at end of file
+pub mod crc32c;
+pub mod endian;
+pub mod varint;
@@ -0,0 +1,60 @@
+use alloc::vec::Vec;
+
+use crate::core::error::DecodeError;
+
+pub(crate) const MAX_VARINT_LEN: usize = 10;
+
+/// append `v` as a LEB128 varint
+pub fn write_varint(buf: &mut Vec<u8>, mut v: u64) {
+    while v >= 0x80 {
+        buf.push((v as u8) | 0x80);
+        v >>= 7;
+    }
+    buf.push(v as u8);
+}
+
+/// read a LEB128 varint starting at `pos` and advance `pos` past it
+pub fn read_varint(buf: &[u8], pos: &mut usize) -> Result<u64, DecodeError> {
+    let mut v = 0u64;
+    for i in 0..MAX_VARINT_LEN {
+        let b = *buf.get(*pos + i).ok_or(DecodeError::TooShort)?;
+        // the 10th byte only carries bit 63, anything above it would overflow
+        if i == MAX_VARINT_LEN - 1 && b > 1 {
+            return Err(DecodeError::InvalidVarint);
+        }
+        v |= ((b & 0x7f) as u64) << (7 * i);
+        if b < 0x80 {
+            *pos += i + 1;
+            return Ok(v);
+        }
+    }
+    Err(DecodeError::InvalidVarint)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::core::error::DecodeError;
+    use crate::util::varint::{read_varint, write_varint};
+
+    #[test]
+    pub fn test_varint() {
+        let values = [0u64, 1, 127, 128, 300, 1 << 32, u64::MAX];
+        let mut buf = Vec::new();
+        for v in values {
+            write_varint(&mut buf, v);
+        }
+        assert_eq!(1 + 1 + 1 + 2 + 2 + 5 + 10, buf.len());
+        let mut pos = 0;
+        for v in values {
+            assert_eq!(v, read_varint(&buf, &mut pos).unwrap());
+        }
+        assert_eq!(buf.len(), pos);
+        assert_eq!(DecodeError::TooShort, read_varint(&[0x80], &mut 0).unwrap_err());
+        assert_eq!(DecodeError::InvalidVarint, read_varint(&[0xff; 11], &mut 0).unwrap_err());
+        let mut overflow = [0xff; 10];
+        overflow[9] = 0x02;
+        assert_eq!(DecodeError::InvalidVarint, read_varint(&overflow, &mut 0).unwrap_err());
+        overflow[9] = 0x01;
+        assert_eq!(u64::MAX, read_varint(&overflow, &mut 0).unwrap());
+    }
+}
@@ -0,0 +1,47 @@
+use alloc::vec::Vec;
+
+use crate::core::codec::{Decoder, Encoder};
+use crate::core::error::DecodeError;
+use crate::core::symbol_table::SymbolTableBuilder;
+
+/// compress the input into a self-describing block, see `Encoder::encode_bytes_self_describing`
+/// takes and returns plain bytes so it is easy to export with `wasm-bindgen`
+/// the input is stored verbatim if the encoding would not be smaller than it
+pub fn compress(input: &[u8]) -> Vec<u8> {
+    let symbol_table = SymbolTableBuilder::build_from_bytes(input);
+    let encoding = Encoder::from_table(&symbol_table).encode_bytes_self_describing(input);
+    let identity_table = SymbolTableBuilder::identity_table();
+    if encoding.len() < identity_table.dump_len() + input.len() {
+        return encoding;
+    }
+    Encoder::from_table(&identity_table).encode_bytes_self_describing(input)
+}
+
+/// decompress a block written by `compress`
+pub fn decompress(input: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    let (_, decoded) = Decoder::decode_bytes_self_describing(input)?;
+    Ok(decoded)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::core::error::DecodeError;
+    use crate::core::symbol_table::SymbolTableBuilder;
+    use crate::wasm::{compress, decompress};
+
+    #[test]
+    pub fn test_compress() {
+        let text = crate::read_string_lines("assets/test_data/l_comment").unwrap()[..100].join("\n");
+        let compressed = compress(text.as_bytes());
+        assert!(compressed.len() < text.len());
+        assert_eq!(text.as_bytes(), decompress(&compressed).unwrap().as_slice());
+
+        let stored_len = |input: &[u8]| SymbolTableBuilder::identity_table().dump_len() + 1 + input.len();
+        for input in [&b""[..], &[0xff, 0, 0xfe], b"a"] {
+            let compressed = compress(input);
+            assert_eq!(stored_len(input), compressed.len());
+            assert_eq!(input, decompress(&compressed).unwrap().as_slice());
+        }
+        assert_eq!(Err(DecodeError::BadMagic), decompress(b"not fsst"));
+    }
+}